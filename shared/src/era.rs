@@ -0,0 +1,199 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail};
+use chrono::Month;
+use num_traits::FromPrimitive;
+
+use crate::YearMonth;
+
+/// Japanese eras (元号)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Era {
+    Meiji,
+    Taisho,
+    Showa,
+    Heisei,
+    Reiwa,
+}
+
+impl Era {
+    const ALL: [Era; 5] = [Era::Meiji, Era::Taisho, Era::Showa, Era::Heisei, Era::Reiwa];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Era::Meiji => "明治",
+            Era::Taisho => "大正",
+            Era::Showa => "昭和",
+            Era::Heisei => "平成",
+            Era::Reiwa => "令和",
+        }
+    }
+
+    /// The month in which the era began. A month in which the era changed is
+    /// attributed to the new era, e.g. 2019-05 is 令和元年5月.
+    pub fn first_month(self) -> YearMonth {
+        let (year, month) = match self {
+            Era::Meiji => (1868, Month::October),
+            Era::Taisho => (1912, Month::July),
+            Era::Showa => (1926, Month::December),
+            Era::Heisei => (1989, Month::January),
+            Era::Reiwa => (2019, Month::May),
+        };
+        YearMonth { year, month }
+    }
+
+    /// Returns the era the given month belongs to, or `None` if it predates Meiji.
+    pub fn of(month: YearMonth) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .rev()
+            .find(|era| era.first_month() <= month)
+    }
+
+    /// Converts a year of this era into a Gregorian year.
+    pub fn to_gregorian_year(self, era_year: i32) -> i32 {
+        self.first_month().year + era_year - 1
+    }
+}
+
+impl FromStr for Era {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|era| era.name() == s)
+            .ok_or_else(|| anyhow!("Unknown era: {s}"))
+    }
+}
+
+/// A month written in the Japanese calendar such as 令和5年4月
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EraYearMonth {
+    pub era: Era,
+    pub year: i32,
+    pub month: Month,
+}
+
+impl EraYearMonth {
+    pub fn from_year_month(month: YearMonth) -> Option<Self> {
+        let era = Era::of(month)?;
+        Some(Self {
+            era,
+            year: month.year - era.first_month().year + 1,
+            month: month.month,
+        })
+    }
+}
+
+impl From<EraYearMonth> for YearMonth {
+    fn from(month: EraYearMonth) -> Self {
+        YearMonth {
+            year: month.era.to_gregorian_year(month.year),
+            month: month.month,
+        }
+    }
+}
+
+impl Display for EraYearMonth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}年{}月",
+            self.era.name(),
+            EraYear(self.year),
+            self.month.number_from_month()
+        )
+    }
+}
+
+impl FromStr for EraYearMonth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow!("Invalid year/month format. The correct form is 令和5年4月: {s}");
+        let s = normalize_digits(s.trim());
+        let (era, year, rest) = split_era_year(&s).ok_or_else(invalid)?;
+        let month = rest.strip_suffix('月').ok_or_else(invalid)?;
+        let month = month
+            .parse::<u64>()
+            .ok()
+            .and_then(Month::from_u64)
+            .ok_or_else(|| anyhow!("Invalid month: {month}"))?;
+        let this = Self { era, year, month };
+        if YearMonth::from(this) < era.first_month() {
+            bail!("{s} is before the beginning of {}", era.name());
+        }
+        Ok(this)
+    }
+}
+
+/// Splits `令和5年...` into the era, the era year and the remainder.
+pub(crate) fn split_era_year(s: &str) -> Option<(Era, i32, &str)> {
+    let era = Era::ALL.into_iter().find(|era| s.starts_with(era.name()))?;
+    let (year, rest) = s[era.name().len()..].split_once('年')?;
+    let year = match year {
+        "元" => 1,
+        // No era has lasted for 100 years, and larger ones could overflow
+        _ => year.parse().ok().filter(|year| (1..100).contains(year))?,
+    };
+    Some((era, year, rest))
+}
+
+/// Replaces full-width digits, which are common in Japanese forms, with ASCII digits.
+pub(crate) fn normalize_digits(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '０'..='９' => char::from(b'0' + (c as u32 - '０' as u32) as u8),
+            _ => c,
+        })
+        .collect()
+}
+
+/// Formats the first year of an era as 元年.
+pub(crate) struct EraYear(pub i32);
+
+impl Display for EraYear {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 == 1 {
+            write!(f, "元")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ym(year: i32, month: Month) -> YearMonth {
+        YearMonth { year, month }
+    }
+
+    #[test]
+    fn format_era() {
+        let format = |month| EraYearMonth::from_year_month(month).unwrap().to_string();
+        assert_eq!(format(ym(2023, Month::April)), "令和5年4月");
+        assert_eq!(format(ym(2019, Month::May)), "令和元年5月");
+        assert_eq!(format(ym(2019, Month::April)), "平成31年4月");
+        assert_eq!(format(ym(1989, Month::January)), "平成元年1月");
+    }
+
+    #[test]
+    fn parse_era() {
+        let parse = |s| YearMonth::from(EraYearMonth::from_str(s).unwrap());
+        assert_eq!(parse("令和5年4月"), ym(2023, Month::April));
+        assert_eq!(parse("令和元年5月"), ym(2019, Month::May));
+        assert_eq!(parse("平成30年12月"), ym(2018, Month::December));
+        assert_eq!(parse("令和５年１０月"), ym(2023, Month::October));
+    }
+
+    #[test]
+    fn invalid_era() {
+        assert!(EraYearMonth::from_str("令和5年13月").is_err());
+        assert!(EraYearMonth::from_str("令和0年4月").is_err());
+        assert!(EraYearMonth::from_str("令和元年4月").is_err());
+        assert!(EraYearMonth::from_str("2023-04").is_err());
+    }
+}
@@ -1,37 +1,134 @@
-use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+mod era;
+mod i18n;
+mod plan;
+mod range_spec;
+mod report;
+mod settings;
+mod validation;
+
+use std::{
+    fmt::Display,
+    iter::FusedIterator,
+    ops::{Add, RangeInclusive, Sub},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use anyhow::anyhow;
+use chrono::{DateTime, Local};
 pub use chrono::{Datelike, Month};
 use num_traits::FromPrimitive;
-use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub use era::{Era, EraYearMonth};
+pub use i18n::{Locale, Text};
+pub use plan::{Plan, PlanAction, PlannedFile, PlannedMonth};
+pub use range_spec::{format_fiscal_year, RangeSpec};
+pub use report::{
+    format_size, ExportedMonth, FileAction, MonthReport, MonthStatus, RunReport, SinkReport,
+};
+pub use settings::{
+    parse_maintenance_wait, NetworkSettings, OutputFormat, OverwritePolicy, Schedule, Settings,
+    SinkConfig, MAX_MAINTENANCE_WAIT_MINS,
+};
+pub use validation::{
+    check_email, check_months, check_passphrase, check_password, check_range, CommandError,
+    InputError, MAX_MONTHS,
+};
 
-#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Credentials {
     pub email: String,
     pub password: String,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// An extra step of signing in, e.g. entering a code sent by email, that
+/// waits for input from the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoginStep {
+    /// What the page asks for, in ixsie's words
+    pub message: String,
+}
+
+/// A calendar month. Serialized as a `YYYY-MM` string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct YearMonth {
     pub year: i32,
     pub month: Month,
 }
 
 impl YearMonth {
-    pub fn iter_range(range: &RangeInclusive<Self>) -> impl Iterator<Item = Self> + '_ {
-        range.start().take_while(|month| *month <= *range.end())
+    /// The current month in the local time zone
+    pub fn current() -> Self {
+        let today = chrono::Local::now().date_naive();
+        Self {
+            year: today.year(),
+            month: Month::from_u32(today.month()).expect("chrono returns a valid month"),
+        }
+    }
+
+    /// The fiscal (school) year this month belongs to. Fiscal years start in April.
+    pub fn fiscal_year(&self) -> i32 {
+        if self.month.number_from_month() >= Month::April.number_from_month() {
+            self.year
+        } else {
+            self.year - 1
+        }
+    }
+
+    /// April of `fiscal_year` to March of the following year
+    pub fn fiscal_year_range(fiscal_year: i32) -> YearMonthRange {
+        YearMonthRange::new(
+            Self {
+                year: fiscal_year,
+                month: Month::April,
+            },
+            Self {
+                year: fiscal_year + 1,
+                month: Month::March,
+            },
+        )
+    }
+
+    pub fn add_months(self, months: i32) -> Self {
+        Self::from_index(self.index() + months)
+    }
+
+    pub fn sub_months(self, months: i32) -> Self {
+        Self::from_index(self.index() - months)
+    }
+
+    /// The next month
+    pub fn succ(self) -> Self {
+        self.add_months(1)
+    }
+
+    /// The previous month
+    pub fn pred(self) -> Self {
+        self.sub_months(1)
+    }
+
+    /// The number of months from `earlier` to `self`, negative if `earlier` is later.
+    pub fn months_since(self, earlier: Self) -> i32 {
+        self.index() - earlier.index()
+    }
+
+    /// The number of months since January of year 0
+    fn index(&self) -> i32 {
+        self.year * 12 + self.month.number_from_month() as i32 - 1
+    }
+
+    fn from_index(index: i32) -> Self {
+        Self {
+            year: index.div_euclid(12),
+            month: Month::from_i32(index.rem_euclid(12) + 1).expect("month index is in 1..=12"),
+        }
     }
 }
 
 impl PartialOrd for YearMonth {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let same_year = self.year.partial_cmp(&other.year)?;
-        let same_month = self
-            .month
-            .number_from_month()
-            .partial_cmp(&other.month.number_from_month())?;
-        Some(same_year.then(same_month))
+        Some(self.cmp(other))
     }
 }
 
@@ -45,16 +142,27 @@ impl Ord for YearMonth {
     }
 }
 
-impl Iterator for YearMonth {
-    type Item = Self;
+impl Add<i32> for YearMonth {
+    type Output = Self;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let this = *self;
-        self.month = this.month.succ();
-        if self.month.number_from_month() < this.month.number_from_month() {
-            self.year += 1;
-        }
-        Some(this)
+    fn add(self, months: i32) -> Self {
+        self.add_months(months)
+    }
+}
+
+impl Sub<i32> for YearMonth {
+    type Output = Self;
+
+    fn sub(self, months: i32) -> Self {
+        self.sub_months(months)
+    }
+}
+
+impl Sub for YearMonth {
+    type Output = i32;
+
+    fn sub(self, earlier: Self) -> i32 {
+        self.months_since(earlier)
     }
 }
 
@@ -68,18 +176,132 @@ impl FromStr for YearMonth {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let re = Regex::new(r"(\d{4})-(\d{2})")?;
-        let cap = re.captures(s).ok_or_else(|| {
-            anyhow!("Invalid year/month format. The correct form is YYYY-MM: {s}")
-        })?;
+        let invalid = || anyhow!("Invalid year/month format. The correct form is YYYY-MM: {s}");
+        let (year, month) = s.split_once('-').ok_or_else(invalid)?;
+        let is_digits = |s: &str, len| s.len() == len && s.bytes().all(|b| b.is_ascii_digit());
+        if !is_digits(year, 4) || !is_digits(month, 2) {
+            return Err(invalid());
+        }
         Ok(YearMonth {
-            year: cap[1].parse()?,
-            month: Month::from_u64(cap[2].parse::<u64>()?)
-                .ok_or_else(|| anyhow!("Invalid month: {}", &cap[2]))?,
+            year: year.parse()?,
+            month: Month::from_u64(month.parse::<u64>()?)
+                .ok_or_else(|| anyhow!("Invalid month: {month}"))?,
         })
     }
 }
 
+impl Serialize for YearMonth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for YearMonth {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// An inclusive range of months. Empty if `start` is after `end`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct YearMonthRange {
+    pub start: YearMonth,
+    pub end: YearMonth,
+}
+
+impl YearMonthRange {
+    pub fn new(start: YearMonth, end: YearMonth) -> Self {
+        Self { start, end }
+    }
+
+    /// The number of months in the range
+    pub fn len(&self) -> usize {
+        (self.end.months_since(self.start) + 1).max(0) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start > self.end
+    }
+
+    pub fn contains(&self, month: &YearMonth) -> bool {
+        self.start <= *month && *month <= self.end
+    }
+
+    pub fn iter(&self) -> YearMonthIter {
+        (*self).into_iter()
+    }
+}
+
+impl From<RangeInclusive<YearMonth>> for YearMonthRange {
+    fn from(range: RangeInclusive<YearMonth>) -> Self {
+        let (start, end) = range.into_inner();
+        Self { start, end }
+    }
+}
+
+impl From<YearMonthRange> for RangeInclusive<YearMonth> {
+    fn from(range: YearMonthRange) -> Self {
+        range.start..=range.end
+    }
+}
+
+impl Display for YearMonthRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+impl IntoIterator for YearMonthRange {
+    type Item = YearMonth;
+    type IntoIter = YearMonthIter;
+
+    fn into_iter(self) -> YearMonthIter {
+        YearMonthIter {
+            front: self.start.index(),
+            back: self.end.index(),
+        }
+    }
+}
+
+/// Iterator over the months of a [`YearMonthRange`]
+#[derive(Debug, Clone)]
+pub struct YearMonthIter {
+    front: i32,
+    back: i32,
+}
+
+impl Iterator for YearMonthIter {
+    type Item = YearMonth;
+
+    fn next(&mut self) -> Option<YearMonth> {
+        if self.front > self.back {
+            return None;
+        }
+        self.front += 1;
+        Some(YearMonth::from_index(self.front - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.front + 1).max(0) as usize;
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for YearMonthIter {
+    fn next_back(&mut self) -> Option<YearMonth> {
+        if self.front > self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(YearMonth::from_index(self.back + 1))
+    }
+}
+
+impl ExactSizeIterator for YearMonthIter {}
+
+impl FusedIterator for YearMonthIter {}
+
 /// Messages from the backend to the frontend
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Message {
@@ -87,11 +309,25 @@ pub enum Message {
     Message(String),
     /// Error output
     Error(String),
+    /// A month is being downloaded
+    Started(YearMonth),
+    /// Result of a month as soon as it is done, for the status table
+    Finished(MonthReport),
     /// Download completion message
     Complete(YearMonth),
+    /// Download failure of a month
+    Failed(YearMonth, String),
+    /// What was done with the destination of a month
+    Decision(YearMonth, FileAction, PathBuf),
+    /// Summary sent once all months have been processed
+    Report(RunReport),
+    /// ixsie is under maintenance. The export resumes at the given time, or
+    /// gives up if there is none.
+    Unavailable(Option<DateTime<Local>>),
 }
 
 impl Message {
+    #[allow(clippy::self_named_constructors)]
     pub fn message(message: String) -> Self {
         Self::Message(message)
     }
@@ -99,7 +335,28 @@ impl Message {
         Self::Error(message)
     }
     pub fn is_err(&self) -> bool {
-        matches!(self, Self::Error(_))
+        matches!(self, Self::Error(_) | Self::Failed(..))
+    }
+    /// The month the message is about, if any
+    pub fn month(&self) -> Option<YearMonth> {
+        match self {
+            Self::Started(month)
+            | Self::Complete(month)
+            | Self::Failed(month, _)
+            | Self::Decision(month, ..) => Some(*month),
+            Self::Finished(report) => Some(report.month),
+            Self::Message(_) | Self::Error(_) | Self::Report(_) | Self::Unavailable(_) => None,
+        }
+    }
+    /// What an [`Unavailable`](Self::Unavailable) message tells the user
+    pub fn maintenance_text(&self, locale: Locale) -> Option<String> {
+        match self {
+            Self::Unavailable(Some(resume_at)) => {
+                Some(Text::MaintenanceWaiting.fill(locale, &[&resume_at.format("%H:%M")]))
+            }
+            Self::Unavailable(None) => Some(Text::Maintenance.get(locale).into()),
+            _ => None,
+        }
     }
 }
 
@@ -117,6 +374,8 @@ impl From<anyhow::Error> for Message {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use super::*;
 
     #[test]
@@ -132,7 +391,10 @@ mod tests {
 
     #[test]
     fn invalid_year_month() {
-        assert!(YearMonth::from_str("2020-13").is_err())
+        assert!(YearMonth::from_str("2020-13").is_err());
+        assert!(YearMonth::from_str("2020-011x").is_err());
+        assert!(YearMonth::from_str("x2020-01").is_err());
+        assert!(YearMonth::from_str("2020-1").is_err());
     }
 
     #[test]
@@ -153,14 +415,35 @@ mod tests {
         assert!(jan_2021 < feb_2021);
     }
 
+    #[test]
+    fn year_month_fiscal_year() {
+        let mar_2024 = YearMonth {
+            year: 2024,
+            month: Month::March,
+        };
+        let apr_2024 = YearMonth {
+            year: 2024,
+            month: Month::April,
+        };
+        assert_eq!(mar_2024.fiscal_year(), 2023);
+        assert_eq!(apr_2024.fiscal_year(), 2024);
+        assert_eq!(YearMonth::fiscal_year_range(2023).len(), 12);
+    }
+
     #[test]
     fn year_month_iter() {
         let dec_2020 = YearMonth {
             year: 2020,
             month: Month::December,
         };
+        let feb_2021 = YearMonth {
+            year: 2021,
+            month: Month::February,
+        };
+        let range = YearMonthRange::new(dec_2020, feb_2021);
+        assert_eq!(range.len(), 3);
         assert_eq!(
-            dec_2020.take(3).collect::<Vec<_>>(),
+            range.iter().collect::<Vec<_>>(),
             vec![
                 YearMonth {
                     year: 2020,
@@ -175,6 +458,59 @@ mod tests {
                     month: Month::February
                 }
             ]
-        )
+        );
+        assert_eq!(range.iter().next_back(), Some(feb_2021));
+        assert!(YearMonthRange::new(feb_2021, dec_2020).is_empty());
+        assert_eq!(YearMonthRange::new(feb_2021, dec_2020).iter().count(), 0);
+    }
+
+    #[test]
+    fn year_month_serde() {
+        let month = YearMonth {
+            year: 2020,
+            month: Month::January,
+        };
+        let json = serde_json::to_string(&month).unwrap();
+        assert_eq!(json, r#""2020-01""#);
+        assert_eq!(serde_json::from_str::<YearMonth>(&json).unwrap(), month);
+        assert!(serde_json::from_str::<YearMonth>(r#""2020-1""#).is_err());
+    }
+
+    fn arb_year_month() -> impl Strategy<Value = YearMonth> {
+        (1900..2200i32, 1..=12u32).prop_map(|(year, month)| YearMonth {
+            year,
+            month: Month::from_u32(month).unwrap(),
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn prop_display_round_trip(month in arb_year_month()) {
+            prop_assert_eq!(YearMonth::from_str(&month.to_string()).unwrap(), month);
+        }
+
+        #[test]
+        fn prop_ordering_matches_arithmetic(a in arb_year_month(), b in arb_year_month()) {
+            prop_assert_eq!(a.cmp(&b), (a - b).cmp(&0));
+            prop_assert_eq!(b + (a - b), a);
+        }
+
+        #[test]
+        fn prop_add_sub_inverse(month in arb_year_month(), n in -1200..1200i32) {
+            prop_assert_eq!(month.add_months(n).sub_months(n), month);
+        }
+
+        #[test]
+        fn prop_range_iter(a in arb_year_month(), b in arb_year_month()) {
+            let range = YearMonthRange::new(a, b);
+            let months = range.iter().collect::<Vec<_>>();
+            prop_assert_eq!(months.len(), range.len());
+            prop_assert!(months.windows(2).all(|w| w[0] < w[1] && w[0].succ() == w[1]));
+            prop_assert!(months.iter().all(|month| range.contains(month)));
+            prop_assert_eq!(months.first().copied(), (!range.is_empty()).then_some(a));
+            let mut reversed = range.iter().rev().collect::<Vec<_>>();
+            reversed.reverse();
+            prop_assert_eq!(reversed, months);
+        }
     }
 }
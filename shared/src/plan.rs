@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Locale, Text, YearMonth, YearMonthRange};
+
+/// What an export would do, computed without writing anything
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Plan {
+    pub range: YearMonthRange,
+    pub save_location: PathBuf,
+    /// The archive the months would be written into, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<PlannedFile>,
+    /// Per-month decisions in chronological order
+    pub months: Vec<PlannedMonth>,
+}
+
+impl Plan {
+    pub fn count(&self, action: PlanAction) -> usize {
+        self.months.iter().filter(|m| m.action == action).count()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub action: PlanAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedMonth {
+    pub month: YearMonth,
+    pub action: PlanAction,
+    /// Destination of the month, or its entry name inside the archive
+    pub path: PathBuf,
+    /// Checksum recorded for the month by the previous run in the same
+    /// location
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAction {
+    /// The destination doesn't exist yet
+    Download,
+    /// The destination exists and would be replaced
+    Overwrite,
+    /// The destination exists and would be left alone
+    Skip,
+    /// The destination exists, so the month would be written to a new name
+    KeepBoth,
+    /// The destination exists and would be replaced if the download differs
+    IfChanged,
+}
+
+impl PlanAction {
+    pub const ALL: [PlanAction; 5] = [
+        PlanAction::Download,
+        PlanAction::Overwrite,
+        PlanAction::Skip,
+        PlanAction::KeepBoth,
+        PlanAction::IfChanged,
+    ];
+
+    pub fn label(self, locale: Locale) -> &'static str {
+        match self {
+            PlanAction::Download => Text::PlanDownload,
+            PlanAction::Overwrite => Text::PlanOverwrite,
+            PlanAction::Skip => Text::PlanSkip,
+            PlanAction::KeepBoth => Text::PlanKeepBoth,
+            PlanAction::IfChanged => Text::PlanIfChanged,
+        }
+        .get(locale)
+    }
+}
@@ -0,0 +1,247 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    era::{normalize_digits, split_era_year, EraYear},
+    Era, EraYearMonth, Month, YearMonth, YearMonthRange, MAX_MONTHS,
+};
+
+/// A user-supplied description of the months to export
+///
+/// Accepted forms:
+///
+/// * `2023-04` or `令和5年4月`: a single month
+/// * `2023-04..2024-03`: an inclusive range of months
+/// * `FY2023` or `令和5年度`: a fiscal year, i.e. 2023-04..2024-03
+/// * `last-12-months`: the last 12 months including the current one, up to
+///   [`MAX_MONTHS`]
+/// * `this-fiscal-year` / `last-fiscal-year`: relative fiscal years
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeSpec {
+    Months(YearMonthRange),
+    FiscalYear(i32),
+    LastMonths(u32),
+    ThisFiscalYear,
+    LastFiscalYear,
+}
+
+impl RangeSpec {
+    /// Resolves the spec into a range of months relative to `today`. Relative
+    /// specs and a fiscal year under way never extend past `today`.
+    pub fn resolve(&self, today: YearMonth) -> YearMonthRange {
+        match self {
+            RangeSpec::Months(range) => *range,
+            RangeSpec::FiscalYear(year) => {
+                let range = YearMonth::fiscal_year_range(*year);
+                // A fiscal year yet to start is left for validation to reject
+                if range.start <= today {
+                    YearMonthRange::new(range.start, range.end.min(today))
+                } else {
+                    range
+                }
+            }
+            RangeSpec::LastMonths(n) => {
+                let n = (*n).clamp(1, MAX_MONTHS as u32) as i32;
+                YearMonthRange::new(today - (n - 1), today)
+            }
+            RangeSpec::ThisFiscalYear => {
+                let range = YearMonth::fiscal_year_range(today.fiscal_year());
+                YearMonthRange::new(range.start, today)
+            }
+            RangeSpec::LastFiscalYear => YearMonth::fiscal_year_range(today.fiscal_year() - 1),
+        }
+    }
+}
+
+impl Display for RangeSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RangeSpec::Months(range) if range.start == range.end => write!(f, "{}", range.start),
+            RangeSpec::Months(range) => write!(f, "{range}"),
+            RangeSpec::FiscalYear(year) => write!(f, "FY{year}"),
+            RangeSpec::LastMonths(n) => write!(f, "last-{n}-months"),
+            RangeSpec::ThisFiscalYear => write!(f, "this-fiscal-year"),
+            RangeSpec::LastFiscalYear => write!(f, "last-fiscal-year"),
+        }
+    }
+}
+
+impl FromStr for RangeSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = normalize_digits(s.trim());
+        match s.as_str() {
+            "this-fiscal-year" => return Ok(RangeSpec::ThisFiscalYear),
+            "last-fiscal-year" => return Ok(RangeSpec::LastFiscalYear),
+            _ => {}
+        }
+        if let Some(n) = s
+            .strip_prefix("last-")
+            .and_then(|rest| rest.strip_suffix("-months"))
+        {
+            let n = n
+                .parse::<u32>()
+                .ok()
+                .filter(|n| (1..=MAX_MONTHS as u32).contains(n))
+                .ok_or_else(|| {
+                    anyhow!("Invalid number of months: {n}. It must be 1 to {MAX_MONTHS}")
+                })?;
+            return Ok(RangeSpec::LastMonths(n));
+        }
+        if let Some(year) = s.strip_prefix("FY") {
+            if year.len() != 4 || !year.bytes().all(|b| b.is_ascii_digit()) {
+                bail!("Invalid fiscal year: {year}. The correct form is FY2023");
+            }
+            return Ok(RangeSpec::FiscalYear(year.parse()?));
+        }
+        if let Some((era, year, "度")) = split_era_year(&s) {
+            return Ok(RangeSpec::FiscalYear(era.to_gregorian_year(year)));
+        }
+        let (start, end) = match s.split_once("..") {
+            Some((start, end)) => (parse_month(start)?, parse_month(end)?),
+            None => {
+                let month = parse_month(&s)?;
+                (month, month)
+            }
+        };
+        if start > end {
+            bail!("The range is reversed: {s}");
+        }
+        Ok(RangeSpec::Months(YearMonthRange::new(start, end)))
+    }
+}
+
+/// Parses a month in either `YYYY-MM` or the Japanese calendar.
+fn parse_month(s: &str) -> anyhow::Result<YearMonth> {
+    YearMonth::from_str(s)
+        .or_else(|_| EraYearMonth::from_str(s).map(YearMonth::from))
+        .map_err(|_| anyhow!("Invalid month: {s}. The correct form is YYYY-MM or 令和5年4月"))
+}
+
+/// Formats a fiscal year as 令和5年度. A fiscal year is named after the latest
+/// era that began in its first calendar year, so FY2019 is 令和元年度.
+pub fn format_fiscal_year(year: i32) -> Option<String> {
+    let era = Era::of(YearMonth {
+        year,
+        month: Month::December,
+    })?;
+    let era_year = year - era.first_month().year + 1;
+    Some(format!("{}{}年度", era.name(), EraYear(era_year)))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn ym(year: i32, month: Month) -> YearMonth {
+        YearMonth { year, month }
+    }
+
+    #[test]
+    fn fiscal_year() {
+        let fy2023 = YearMonthRange::new(ym(2023, Month::April), ym(2024, Month::March));
+        assert_eq!(
+            RangeSpec::from_str("FY2023")
+                .unwrap()
+                .resolve(ym(2025, Month::January)),
+            fy2023
+        );
+        assert_eq!(
+            RangeSpec::from_str("令和5年度").unwrap(),
+            RangeSpec::FiscalYear(2023)
+        );
+        assert_eq!(format_fiscal_year(2019).unwrap(), "令和元年度");
+    }
+
+    #[test]
+    fn relative() {
+        let today = ym(2024, Month::February);
+        assert_eq!(
+            RangeSpec::from_str("last-12-months")
+                .unwrap()
+                .resolve(today),
+            YearMonthRange::new(ym(2023, Month::March), today)
+        );
+        assert_eq!(
+            RangeSpec::ThisFiscalYear.resolve(today),
+            YearMonthRange::new(ym(2023, Month::April), today)
+        );
+        assert_eq!(
+            RangeSpec::FiscalYear(2023).resolve(today),
+            YearMonthRange::new(ym(2023, Month::April), today)
+        );
+        assert_eq!(
+            RangeSpec::FiscalYear(2022).resolve(today),
+            YearMonthRange::new(ym(2022, Month::April), ym(2023, Month::March))
+        );
+        assert_eq!(
+            RangeSpec::LastFiscalYear.resolve(today),
+            YearMonthRange::new(ym(2022, Month::April), ym(2023, Month::March))
+        );
+    }
+
+    #[test]
+    fn current_fiscal_year_is_accepted() {
+        let today = YearMonth::current();
+        let range = RangeSpec::FiscalYear(today.fiscal_year()).resolve(today);
+        assert_eq!(range.end, today);
+        assert_eq!(crate::check_range(range, today), Ok(()));
+    }
+
+    #[test]
+    fn explicit_months() {
+        assert_eq!(
+            RangeSpec::from_str("2023-04..令和6年3月").unwrap(),
+            RangeSpec::Months(YearMonthRange::new(
+                ym(2023, Month::April),
+                ym(2024, Month::March)
+            ))
+        );
+        assert!(RangeSpec::from_str("2024-03..2023-04").is_err());
+        assert!(RangeSpec::from_str("last-0-months").is_err());
+        assert!(RangeSpec::from_str("last-121-months").is_err());
+        assert!(RangeSpec::from_str("FY2147483647").is_err());
+        assert!(RangeSpec::from_str("FY-2023").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_last_months_are_bounded(n in any::<u64>()) {
+            let today = ym(2024, Month::February);
+            if let Ok(spec) = RangeSpec::from_str(&format!("last-{n}-months")) {
+                prop_assert!((1..=MAX_MONTHS as u64).contains(&n));
+                prop_assert_eq!(spec.resolve(today).len() as u64, n);
+            }
+            let range = RangeSpec::LastMonths(n as u32).resolve(today);
+            prop_assert!(range.len() <= MAX_MONTHS && range.end == today);
+        }
+
+        #[test]
+        fn prop_fiscal_years_have_four_digits(year in any::<i32>()) {
+            let parsed = RangeSpec::from_str(&format!("FY{year}"));
+            prop_assert_eq!(parsed.is_ok(), (1000..=9999).contains(&year));
+            if let Ok(spec) = parsed {
+                prop_assert_eq!(spec.resolve(ym(2024, Month::February)).len(), 12);
+            }
+            // Must not overflow
+            let _ = RangeSpec::from_str(&format!("令和{year}年度"));
+        }
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for s in [
+            "2023-04",
+            "2023-04..2024-03",
+            "FY2023",
+            "last-12-months",
+            "this-fiscal-year",
+        ] {
+            assert_eq!(RangeSpec::from_str(s).unwrap().to_string(), s);
+        }
+    }
+}
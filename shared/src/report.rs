@@ -0,0 +1,286 @@
+use std::{path::PathBuf, time::Duration};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::{Locale, Text, YearMonth, YearMonthRange};
+
+/// Summary of an export run, written next to the exported files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunReport {
+    pub started_at: DateTime<Local>,
+    pub finished_at: DateTime<Local>,
+    /// From the first to the last requested month
+    pub range: YearMonthRange,
+    pub save_location: PathBuf,
+    /// The archive the months were written into, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive: Option<PathBuf>,
+    /// Per-month results in chronological order
+    pub months: Vec<MonthReport>,
+    /// Results of copying the archive to the backup targets
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<SinkReport>,
+    /// Months of earlier runs this one has no checksum of, kept so that a
+    /// partial run doesn't lose the checksums of the other months
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub earlier: Vec<MonthReport>,
+}
+
+impl RunReport {
+    pub fn count(&self, status: MonthStatus) -> usize {
+        self.months.iter().filter(|m| m.status == status).count()
+    }
+
+    pub fn failed_months(&self) -> impl Iterator<Item = YearMonth> + '_ {
+        self.months
+            .iter()
+            .filter(|m| m.status == MonthStatus::Failed)
+            .map(|m| m.month)
+    }
+
+    /// Months whose contents differ from the previous run
+    pub fn changed_months(&self) -> impl Iterator<Item = YearMonth> + '_ {
+        self.months.iter().filter(|m| m.changed).map(|m| m.month)
+    }
+
+    /// Checksum recorded for `month` by this run or an earlier one
+    pub fn sha256(&self, month: YearMonth) -> Option<&str> {
+        self.months
+            .iter()
+            .chain(&self.earlier)
+            .filter(|m| m.month == month)
+            .find_map(|m| m.sha256.as_deref())
+    }
+
+    /// Keeps the months of `previous` this run has no checksum of in
+    /// [`earlier`](Self::earlier)
+    pub fn carry_over(&mut self, previous: &RunReport) {
+        let mut earlier = Vec::<MonthReport>::new();
+        for month in previous.months.iter().chain(&previous.earlier) {
+            if month.sha256.is_some()
+                && self.sha256(month.month).is_none()
+                && earlier.iter().all(|m| m.month != month.month)
+            {
+                earlier.push(month.clone());
+            }
+        }
+        earlier.sort_by_key(|m| m.month);
+        self.earlier = earlier;
+    }
+
+    /// Total number of bytes written
+    pub fn total_size(&self) -> u64 {
+        self.months.iter().filter_map(|m| m.size).sum()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonthReport {
+    pub month: YearMonth,
+    pub status: MonthStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub duration_ms: u64,
+    /// What was done with the destination
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action: Option<FileAction>,
+    /// The checksum differs from the one recorded by the previous run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub changed: bool,
+    /// Where the replaced file was kept, if its contents changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_version: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Results of copying the file to the backup targets
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<SinkReport>,
+}
+
+impl MonthReport {
+    pub fn done(
+        month: YearMonth,
+        path: PathBuf,
+        size: u64,
+        sha256: String,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            month,
+            status: MonthStatus::Done,
+            path: Some(path),
+            size: Some(size),
+            sha256: Some(sha256),
+            duration_ms: duration.as_millis() as u64,
+            action: None,
+            changed: false,
+            previous_version: None,
+            error: None,
+            sinks: Vec::new(),
+        }
+    }
+
+    /// The existing file at `path` was left alone without downloading
+    pub fn skipped(month: YearMonth, path: PathBuf, duration: Duration) -> Self {
+        Self {
+            month,
+            status: MonthStatus::Done,
+            path: Some(path),
+            size: None,
+            sha256: None,
+            duration_ms: duration.as_millis() as u64,
+            action: Some(FileAction::Skipped),
+            changed: false,
+            previous_version: None,
+            error: None,
+            sinks: Vec::new(),
+        }
+    }
+
+    pub fn failed(month: YearMonth, error: String, duration: Duration) -> Self {
+        Self {
+            month,
+            status: MonthStatus::Failed,
+            path: None,
+            size: None,
+            sha256: None,
+            duration_ms: duration.as_millis() as u64,
+            action: None,
+            changed: false,
+            previous_version: None,
+            error: Some(error),
+            sinks: Vec::new(),
+        }
+    }
+
+    /// Backup targets the file could not be copied to
+    pub fn failed_sinks(&self) -> impl Iterator<Item = &SinkReport> + '_ {
+        self.sinks.iter().filter(|sink| sink.error.is_some())
+    }
+}
+
+/// PDF of a month found in a save location, for the viewer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedMonth {
+    pub month: YearMonth,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SinkReport {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MonthStatus {
+    Done,
+    Failed,
+}
+
+impl MonthStatus {
+    pub fn label(self, locale: Locale) -> &'static str {
+        match self {
+            MonthStatus::Done => Text::Done,
+            MonthStatus::Failed => Text::Failed,
+        }
+        .get(locale)
+    }
+}
+
+/// What an export did with the destination of a month, according to the
+/// [`OverwritePolicy`](crate::OverwritePolicy)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileAction {
+    Created,
+    Overwritten,
+    /// The file existed and was not downloaded
+    Skipped,
+    /// The file existed and the month was written to a new name
+    KeptBoth,
+    /// The downloaded file was identical to the existing one
+    Unchanged,
+}
+
+impl FileAction {
+    pub fn label(self, locale: Locale) -> &'static str {
+        match self {
+            FileAction::Created => Text::ActionCreated,
+            FileAction::Overwritten => Text::ActionOverwritten,
+            FileAction::Skipped => Text::ActionSkipped,
+            FileAction::KeptBoth => Text::ActionKeptBoth,
+            FileAction::Unchanged => Text::ActionUnchanged,
+        }
+        .get(locale)
+    }
+}
+
+/// Formats a byte count for humans, e.g. `1.5 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_of_other_months_are_carried_over() {
+        let month = |s: &str| s.parse::<YearMonth>().unwrap();
+        let done = |s, sha256: &str| {
+            MonthReport::done(month(s), s.into(), 1, sha256.into(), Duration::ZERO)
+        };
+        let report = |months| RunReport {
+            started_at: Local::now(),
+            finished_at: Local::now(),
+            range: YearMonthRange::new(month("2023-04"), month("2023-06")),
+            save_location: PathBuf::new(),
+            archive: None,
+            months,
+            sinks: Vec::new(),
+            earlier: Vec::new(),
+        };
+        let first = report(vec![done("2023-04", "a"), done("2023-05", "b")]);
+        // A retry of a month that fails again
+        let mut second = report(vec![MonthReport::failed(
+            month("2023-05"),
+            "error".into(),
+            Duration::ZERO,
+        )]);
+        second.carry_over(&first);
+        let mut third = report(vec![done("2023-06", "c")]);
+        third.carry_over(&second);
+
+        assert_eq!(third.sha256(month("2023-04")), Some("a"));
+        assert_eq!(third.sha256(month("2023-05")), Some("b"));
+        assert_eq!(third.sha256(month("2023-06")), Some("c"));
+        assert_eq!(third.earlier.len(), 2);
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
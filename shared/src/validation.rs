@@ -0,0 +1,215 @@
+//! Checks of the input of an export, shared by the form and the backend
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Locale, Text, YearMonth, YearMonthRange};
+
+/// Why the input of an export is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputError {
+    EmailMissing,
+    EmailInvalid,
+    PasswordMissing,
+    /// The passphrase and its confirmation differ
+    PassphraseMismatch,
+    /// A month of the range is empty or malformed
+    MonthMissing,
+    /// The first month is after the last one
+    RangeReversed,
+    RangeInFuture,
+    /// The range spans more than [`MAX_MONTHS`]
+    RangeTooLarge,
+    /// No months were requested
+    NoMonths,
+    SaveLocationMissing,
+    SaveLocationNotFound,
+    SaveLocationNotWritable,
+}
+
+impl InputError {
+    pub fn message(self, locale: Locale) -> String {
+        let key = match self {
+            InputError::EmailMissing => Text::EmailMissing,
+            InputError::EmailInvalid => Text::EmailInvalid,
+            InputError::PasswordMissing => Text::PasswordMissing,
+            InputError::PassphraseMismatch => Text::PassphraseMismatch,
+            InputError::MonthMissing => Text::MonthMissing,
+            InputError::RangeReversed => Text::RangeReversed,
+            InputError::RangeInFuture => Text::RangeInFuture,
+            InputError::RangeTooLarge => return Text::RangeTooLarge.fill(locale, &[&MAX_MONTHS]),
+            InputError::NoMonths => Text::NoMonths,
+            InputError::SaveLocationMissing => Text::SaveLocationMissing,
+            InputError::SaveLocationNotFound => Text::SaveLocationNotFound,
+            InputError::SaveLocationNotWritable => Text::SaveLocationNotWritable,
+        };
+        key.get(locale).into()
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message(Locale::current()))
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Error of a command started from the form, telling rejected input apart
+/// from failures of the export itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommandError {
+    Invalid(InputError),
+    Failed(String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Invalid(err) => err.fmt(f),
+            CommandError::Failed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<InputError> for CommandError {
+    fn from(err: InputError) -> Self {
+        Self::Invalid(err)
+    }
+}
+
+/// Ranges longer than this, 10 years, are most likely typos
+pub const MAX_MONTHS: usize = 120;
+
+/// Accepts addresses of the form `local@domain.tld` without whitespace
+pub fn check_email(email: &str) -> Result<(), InputError> {
+    let email = email.trim();
+    if email.is_empty() {
+        return Err(InputError::EmailMissing);
+    }
+    let valid = match email.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && !email.chars().any(char::is_whitespace)
+                && domain
+                    .split_once('.')
+                    .is_some_and(|(host, _)| !host.is_empty() && !domain.ends_with('.'))
+        }
+        None => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(InputError::EmailInvalid)
+    }
+}
+
+pub fn check_password(password: &str) -> Result<(), InputError> {
+    if password.is_empty() {
+        Err(InputError::PasswordMissing)
+    } else {
+        Ok(())
+    }
+}
+
+/// The encryption passphrase must be entered twice the same, since a typo
+/// makes the files impossible to decrypt
+pub fn check_passphrase(passphrase: &str, confirmation: &str) -> Result<(), InputError> {
+    if passphrase == confirmation {
+        Ok(())
+    } else {
+        Err(InputError::PassphraseMismatch)
+    }
+}
+
+/// A range is valid if it isn't reversed, doesn't reach past `current` and
+/// isn't longer than [`MAX_MONTHS`]
+pub fn check_range(range: YearMonthRange, current: YearMonth) -> Result<(), InputError> {
+    if range.is_empty() {
+        Err(InputError::RangeReversed)
+    } else if range.end > current {
+        Err(InputError::RangeInFuture)
+    } else if range.len() > MAX_MONTHS {
+        Err(InputError::RangeTooLarge)
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks the months of a request like the range spanning them
+pub fn check_months(months: &[YearMonth], current: YearMonth) -> Result<(), InputError> {
+    let (Some(&first), Some(&last)) = (months.iter().min(), months.iter().max()) else {
+        return Err(InputError::NoMonths);
+    };
+    check_range(YearMonthRange::new(first, last), current)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn emails_are_checked_loosely() {
+        assert_eq!(check_email("parent@example.com"), Ok(()));
+        assert_eq!(check_email(" parent@mail.example.jp "), Ok(()));
+        assert_eq!(check_email(""), Err(InputError::EmailMissing));
+        for invalid in [
+            "parent",
+            "@example.com",
+            "parent@example",
+            "parent@.com",
+            "parent@example.",
+            "pa rent@example.com",
+            "a@b@example.com",
+        ] {
+            assert_eq!(
+                check_email(invalid),
+                Err(InputError::EmailInvalid),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn passphrases_must_match() {
+        assert_eq!(check_passphrase("", ""), Ok(()));
+        assert_eq!(check_passphrase("secret", "secret"), Ok(()));
+        assert_eq!(
+            check_passphrase("secret", "secrte"),
+            Err(InputError::PassphraseMismatch)
+        );
+    }
+
+    #[test]
+    fn ranges_must_be_ordered_and_past() {
+        let month = |s| YearMonth::from_str(s).unwrap();
+        let current = month("2023-05");
+        let range = |from, to| YearMonthRange::new(month(from), month(to));
+        assert_eq!(check_range(range("2023-04", "2023-05"), current), Ok(()));
+        assert_eq!(check_range(range("2023-05", "2023-05"), current), Ok(()));
+        assert_eq!(
+            check_range(range("2023-05", "2023-04"), current),
+            Err(InputError::RangeReversed)
+        );
+        assert_eq!(
+            check_range(range("2023-04", "2023-06"), current),
+            Err(InputError::RangeInFuture)
+        );
+        assert_eq!(check_range(range("2013-06", "2023-05"), current), Ok(()));
+        assert_eq!(
+            check_range(range("2013-05", "2023-05"), current),
+            Err(InputError::RangeTooLarge)
+        );
+        assert_eq!(check_months(&[], current), Err(InputError::NoMonths));
+        assert_eq!(
+            check_months(&[month("2023-03"), month("2022-04")], current),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Single-file output. Months are downloaded concurrently but archive formats
+//! can only be written sequentially, so the entries are funnelled through a
+//! channel into one blocking writer.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use flate2::{write::GzEncoder, Compression};
+use shared::{Locale, OutputFormat, Text};
+use tempfile::NamedTempFile;
+use tokio::{sync::mpsc, task::JoinHandle};
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+use crate::crypto;
+
+/// Writes entries into an archive at `path`, encrypted with `passphrase` if
+/// given. The archive only appears at `path` once [`ArchiveWriter::finish`]
+/// succeeds. Dropping the writer before that deletes what was written.
+pub struct ArchiveWriter {
+    entries: mpsc::Sender<Queued>,
+    task: JoinHandle<anyhow::Result<()>>,
+}
+
+/// What the blocking writer receives
+enum Queued {
+    Entry(Entry),
+    /// Sent by [`ArchiveWriter::finish`] after the last entry
+    Commit,
+}
+
+struct Entry {
+    name: String,
+    data: Vec<u8>,
+}
+
+enum Archive {
+    Zip(ZipWriter<File>),
+    TarGz(tar::Builder<GzEncoder<File>>),
+}
+
+impl ArchiveWriter {
+    pub fn create(
+        path: PathBuf,
+        format: OutputFormat,
+        passphrase: Option<String>,
+    ) -> anyhow::Result<Self> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let temp = NamedTempFile::new_in(dir)?;
+        let file = temp.reopen()?;
+        let archive = match format {
+            OutputFormat::Files => {
+                anyhow::bail!(Text::NoArchiveFormat.get(Locale::current()))
+            }
+            OutputFormat::Zip => Archive::Zip(ZipWriter::new(file)),
+            OutputFormat::TarGz => Archive::TarGz(tar::Builder::new(GzEncoder::new(
+                file,
+                Compression::default(),
+            ))),
+        };
+        let (entries, mut rx) = mpsc::channel::<Queued>(4);
+        let task = tokio::task::spawn_blocking(move || {
+            let mut archive = archive;
+            loop {
+                match rx.blocking_recv() {
+                    Some(Queued::Entry(entry)) => archive.append(&entry)?,
+                    Some(Queued::Commit) => break,
+                    // The writer was dropped without finishing, so the
+                    // partial archive is deleted with `temp`
+                    None => return Ok(()),
+                }
+            }
+            archive.finish()?;
+            match passphrase {
+                Some(passphrase) => {
+                    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+                    let mut encrypted = NamedTempFile::new_in(dir)?;
+                    let plaintext = BufReader::new(temp.reopen()?);
+                    crypto::encrypt(
+                        plaintext,
+                        BufWriter::new(encrypted.as_file_mut()),
+                        &passphrase,
+                    )?;
+                    encrypted.persist(&path)?;
+                }
+                None => {
+                    temp.persist(&path)?;
+                }
+            }
+            Ok(())
+        });
+        Ok(Self { entries, task })
+    }
+
+    /// Queues an entry. Fails if the writer has already failed, in which case
+    /// [`ArchiveWriter::finish`] returns the cause.
+    pub async fn add(&self, name: String, data: Vec<u8>) -> anyhow::Result<()> {
+        self.entries
+            .send(Queued::Entry(Entry { name, data }))
+            .await
+            .map_err(|_| anyhow!(Text::ArchiveInterrupted.get(Locale::current())))
+    }
+
+    /// Writes the remaining entries and moves the archive into place.
+    pub async fn finish(self) -> anyhow::Result<()> {
+        // If the writer has failed, the task tells why
+        let _ = self.entries.send(Queued::Commit).await;
+        drop(self.entries);
+        self.task.await?
+    }
+}
+
+impl Archive {
+    fn append(&mut self, entry: &Entry) -> anyhow::Result<()> {
+        match self {
+            Archive::Zip(zip) => {
+                // Non-ASCII names are flagged as UTF-8 by the zip crate
+                let options =
+                    FileOptions::default().compression_method(CompressionMethod::Deflated);
+                zip.start_file(entry.name.as_str(), options)?;
+                zip.write_all(&entry.data)?;
+            }
+            Archive::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(entry.data.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(chrono::Utc::now().timestamp().max(0) as u64);
+                tar.append_data(&mut header, &entry.name, entry.data.as_slice())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Archive::Zip(mut zip) => {
+                zip.finish()?;
+            }
+            Archive::TarGz(tar) => {
+                tar.into_inner()?.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    async fn write_concurrently(path: PathBuf, format: OutputFormat) {
+        let writer = ArchiveWriter::create(path, format, None).unwrap();
+        let names = ["2023-04.pdf", "2023-05.pdf", "連絡帳.md"];
+        futures::future::try_join_all(
+            names
+                .iter()
+                .map(|name| writer.add(name.to_string(), name.as_bytes().to_vec())),
+        )
+        .await
+        .unwrap();
+        writer.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn zip_contains_all_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ixsie.zip");
+        write_concurrently(path.clone(), OutputFormat::Zip).await;
+
+        let mut zip = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(zip.len(), 3);
+        let mut content = String::new();
+        zip.by_name("連絡帳.md")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "連絡帳.md");
+    }
+
+    #[tokio::test]
+    async fn tar_gz_contains_all_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ixsie.tar.gz");
+        write_concurrently(path.clone(), OutputFormat::TarGz).await;
+
+        let mut tar = tar::Archive::new(GzDecoder::new(File::open(&path).unwrap()));
+        let mut names = tar
+            .entries()
+            .unwrap()
+            .map(|entry| entry.unwrap().path().unwrap().display().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["2023-04.pdf", "2023-05.pdf", "連絡帳.md"]);
+    }
+
+    #[tokio::test]
+    async fn dropped_writer_leaves_path_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ixsie.zip");
+        std::fs::write(&path, b"previous").unwrap();
+        let writer = ArchiveWriter::create(path.clone(), OutputFormat::Zip, None).unwrap();
+        writer
+            .add("2023-04.pdf".into(), b"%PDF".to_vec())
+            .await
+            .unwrap();
+        let task = writer.task;
+        drop(writer.entries);
+        task.await.unwrap().unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"previous");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
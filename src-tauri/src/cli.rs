@@ -0,0 +1,224 @@
+//! Command line interface. Without a subcommand the desktop app starts.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use futures::future::BoxFuture;
+use shared::{
+    check_passphrase, Credentials, Locale, LoginStep, Message, NetworkSettings, OutputFormat,
+    OverwritePolicy, Plan, PlanAction, RangeSpec, Text, YearMonth,
+};
+use structopt::StructOpt;
+use tempfile::NamedTempFile;
+
+use crate::{
+    crypto,
+    export::{self, Emitter, ExportOptions},
+    validation,
+};
+
+#[derive(StructOpt)]
+#[structopt(about = "Downloads the contact books of ixsie in bulk")]
+pub struct Opt {
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(StructOpt)]
+pub enum Command {
+    /// Downloads the contact books
+    Export(ExportArgs),
+    /// Decrypts a file saved encrypted
+    Decrypt {
+        /// The encrypted file (*.enc)
+        input: PathBuf,
+        /// Where to save the decrypted file. Defaults to the input without .enc
+        #[structopt(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(StructOpt)]
+pub struct ExportArgs {
+    /// Login email address. The password is asked for when run
+    #[structopt(long)]
+    email: String,
+    /// Months to export (e.g. FY2023, 令和5年度, last-12-months, 2023-04..2024-03)
+    #[structopt(long, default_value = "this-fiscal-year")]
+    range: RangeSpec,
+    /// Folder to save into
+    #[structopt(long)]
+    save_location: PathBuf,
+    /// Creates the save location if it doesn't exist
+    #[structopt(long)]
+    create_save_location: bool,
+    /// Output format (files, zip, tar.gz)
+    #[structopt(long, default_value = "files")]
+    format: OutputFormat,
+    /// What to do with existing files (overwrite, skip, keep-both, if-changed)
+    #[structopt(long, default_value = "overwrite")]
+    existing: OverwritePolicy,
+    /// Encrypts the files with a passphrase asked for when run
+    #[structopt(long)]
+    encrypt: bool,
+    /// Logs in and only shows the months to download or overwrite and where,
+    /// without writing anything
+    #[structopt(long)]
+    dry_run: bool,
+    /// While ixsie is under maintenance, waits up to this many minutes (at most
+    /// 1440) and then resumes
+    #[structopt(long, value_name = "minutes", parse(try_from_str = shared::parse_maintenance_wait))]
+    wait_for_maintenance: Option<u64>,
+}
+
+impl Command {
+    pub fn run(self) -> anyhow::Result<()> {
+        match self {
+            Command::Export(args) => tokio::runtime::Runtime::new()?.block_on(export(args)),
+            Command::Decrypt { input, output } => decrypt(input, output),
+        }
+    }
+}
+
+/// Progress messages go to stderr
+struct Console;
+
+impl Emitter for Console {
+    fn emit(&self, message: Message) -> anyhow::Result<()> {
+        let locale = Locale::current();
+        match message {
+            Message::Message(message) => eprintln!("{message}"),
+            Message::Error(err) => eprintln!("{}", Text::ErrorLine.fill(locale, &[&err])),
+            Message::Complete(month) => eprintln!("{}", Text::MonthDone.fill(locale, &[&month])),
+            Message::Failed(month, err) => {
+                eprintln!("{}", Text::MonthFailed.fill(locale, &[&month, &err]))
+            }
+            Message::Decision(month, action, path) => {
+                eprintln!("{month}: {} ({})", action.label(locale), path.display())
+            }
+            Message::Unavailable(_) => {
+                eprintln!("{}", message.maintenance_text(locale).unwrap_or_default())
+            }
+            // The lines of `Complete`, `Failed` and `Decision` say enough
+            Message::Started(_) | Message::Finished(_) | Message::Report(_) => {}
+        }
+        Ok(())
+    }
+
+    fn ask(&self, step: LoginStep) -> BoxFuture<'_, anyhow::Result<String>> {
+        Box::pin(async move {
+            eprintln!("{}", step.message);
+            eprint!("{}: ", Text::VerificationCode.get(Locale::current()));
+            let mut answer = String::new();
+            std::io::stdin().read_line(&mut answer)?;
+            Ok(answer.trim().to_string())
+        })
+    }
+}
+
+async fn export(args: ExportArgs) -> anyhow::Result<()> {
+    let locale = Locale::current();
+    let creds = Credentials {
+        email: args.email,
+        password: rpassword::prompt_password(Text::PasswordPrompt.get(locale))?,
+    };
+    let passphrase = if args.encrypt {
+        let passphrase = rpassword::prompt_password(Text::EncryptionPassphrasePrompt.get(locale))?;
+        let confirmation =
+            rpassword::prompt_password(Text::EncryptionPassphraseConfirmPrompt.get(locale))?;
+        check_passphrase(&passphrase, &confirmation)?;
+        Some(passphrase)
+    } else {
+        None
+    };
+    let months = args
+        .range
+        .resolve(YearMonth::current())
+        .iter()
+        .collect::<Vec<_>>();
+    validation::check_request(
+        &creds,
+        &months,
+        &args.save_location,
+        args.create_save_location,
+    )?;
+    let options = ExportOptions {
+        save_location: args.save_location,
+        format: args.format,
+        policy: args.existing,
+        passphrase,
+        sinks: Vec::new(),
+        network: NetworkSettings {
+            maintenance_wait_mins: args.wait_for_maintenance,
+            ..NetworkSettings::default()
+        },
+    };
+    if args.dry_run {
+        let plan = export::plan(&Console, creds, months, options).await?;
+        print_plan(&plan);
+        return Ok(());
+    }
+    let report = export::start_body(&Console, creds, months, options).await?;
+    if report.failed_months().next().is_some() {
+        anyhow::bail!(Text::SomeMonthsFailed.get(locale));
+    }
+    Ok(())
+}
+
+fn print_plan(plan: &Plan) {
+    let locale = Locale::current();
+    println!("{}: {}", Text::Period.get(locale), plan.range);
+    println!(
+        "{}: {}",
+        Text::SaveLocation.get(locale),
+        plan.save_location.display()
+    );
+    if let Some(archive) = &plan.archive {
+        println!(
+            "{}: {} ({})",
+            Text::Archive.get(locale),
+            archive.path.display(),
+            archive.action.label(locale)
+        );
+    }
+    for month in &plan.months {
+        println!(
+            "{}\t{}\t{}",
+            month.month,
+            month.action.label(locale),
+            month.path.display()
+        );
+    }
+    let counts = PlanAction::ALL
+        .iter()
+        .map(|&action| (action, plan.count(action)))
+        .filter(|&(_, count)| count > 0)
+        .map(|(action, count)| Text::ActionCount.fill(locale, &[&action.label(locale), &count]))
+        .collect::<Vec<_>>();
+    println!("{}", counts.join(" / "));
+}
+
+fn decrypt(input: PathBuf, output: Option<PathBuf>) -> anyhow::Result<()> {
+    let locale = Locale::current();
+    let output = output
+        .or_else(|| crypto::decrypted_path(&input))
+        .context(Text::OutputRequired.get(locale))?;
+    let passphrase = rpassword::prompt_password(Text::PassphrasePrompt.get(locale))?;
+    let reader = BufReader::new(
+        File::open(&input).with_context(|| Text::CannotOpen.fill(locale, &[&input.display()]))?,
+    );
+    // Don't leave a partially decrypted file behind on failure
+    let dir = output
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| ".".as_ref());
+    let mut temp = NamedTempFile::new_in(dir)?;
+    crypto::decrypt(reader, BufWriter::new(temp.as_file_mut()), &passphrase)?;
+    temp.persist(&output)?;
+    eprintln!("{}", Text::Decrypted.fill(locale, &[&output.display()]));
+    Ok(())
+}
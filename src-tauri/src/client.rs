@@ -0,0 +1,149 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use reqwest::{
+    header::{AUTHORIZATION, LOCATION},
+    redirect::Policy,
+    Certificate, Client, Method, Proxy, Request, RequestBuilder, Response, StatusCode,
+};
+use shared::{Locale, NetworkSettings, Text};
+
+use crate::diagnostics;
+
+const DEFAULT_USER_AGENT: &str = concat!("ixsie-exporter/", env!("CARGO_PKG_VERSION"));
+
+/// Redirects [`send`] follows before giving up
+const MAX_REDIRECTS: usize = 10;
+
+/// Builds the HTTP client used for logging in, downloading and uploading to
+/// the backup targets.
+pub fn build(network: &NetworkSettings) -> anyhow::Result<Client> {
+    // Redirects are followed by `send`
+    let mut builder = Client::builder()
+        .cookie_store(true)
+        .redirect(Policy::none())
+        .user_agent(network.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+    if let Some(proxy) = network.proxy.as_deref().filter(|proxy| !proxy.is_empty()) {
+        let proxy = Proxy::all(proxy)
+            .with_context(|| Text::InvalidProxy.fill(Locale::current(), &[&proxy]))?;
+        builder = builder.proxy(proxy);
+    }
+    for path in &network.root_certificates {
+        let cert = std::fs::read(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| {
+                Certificate::from_pem(&bytes)
+                    .or_else(|_| Certificate::from_der(&bytes))
+                    .map_err(anyhow::Error::from)
+            })
+            .with_context(|| {
+                Text::UnreadableCertificate.fill(Locale::current(), &[&path.display()])
+            })?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if let Some(secs) = network.connect_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs(secs));
+    }
+    if let Some(secs) = network.timeout_secs {
+        builder = builder.timeout(Duration::from_secs(secs));
+    }
+    builder
+        .build()
+        .context(Text::ClientFailed.get(Locale::current()))
+}
+
+/// Sends `request` and follows the redirects one by one, so that every hop is
+/// recorded in the request history and the final URL tells where the server
+/// sent us. Like browsers, 301, 302 and 303 continue with a `GET`.
+pub async fn send(client: &Client, request: RequestBuilder) -> anyhow::Result<Response> {
+    let mut request = request.build()?;
+    for _ in 0..=MAX_REDIRECTS {
+        let resend = request.try_clone();
+        let response = diagnostics::send(client, request).await?;
+        let Some(location) = redirect_location(&response) else {
+            return Ok(response);
+        };
+        tracing::debug!(from = %response.url(), to = %location, status = %response.status(), "redirected");
+        request = match response.status() {
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+                // Streamed bodies can't be sent again
+                let mut next = resend.with_context(|| {
+                    Text::UnexpectedRedirect.fill(Locale::current(), &[&location])
+                })?;
+                if next.url().host_str() != location.host_str() {
+                    next.headers_mut().remove(AUTHORIZATION);
+                }
+                *next.url_mut() = location;
+                next
+            }
+            _ => Request::new(Method::GET, location),
+        };
+    }
+    anyhow::bail!(Text::TooManyRedirects.fill(Locale::current(), &[&MAX_REDIRECTS]))
+}
+
+fn redirect_location(response: &Response) -> Option<reqwest::Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_settings() {
+        assert!(build(&NetworkSettings::default()).is_ok());
+        let invalid_proxy = NetworkSettings {
+            proxy: Some("not a url".into()),
+            ..Default::default()
+        };
+        assert!(build(&invalid_proxy).is_err());
+        let missing_cert = NetworkSettings {
+            root_certificates: vec!["/nonexistent/ca.pem".into()],
+            ..Default::default()
+        };
+        let err = build(&missing_cert).unwrap_err().to_string();
+        assert!(err.contains("/nonexistent/ca.pem"));
+    }
+
+    #[tokio::test]
+    async fn redirects_are_followed_with_get() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 303 See Other\r\nlocation: /done\r\ncontent-length: 0\r\n\r\n",
+                "HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok",
+            ] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buf = [0; 1024];
+                let n = stream.read(&mut buf).await.unwrap();
+                let head = String::from_utf8_lossy(&buf[..n]).to_string();
+                requests.push(head.lines().next().unwrap_or_default().to_string());
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let client = build(&NetworkSettings::default()).unwrap();
+        let response = send(&client, client.post(format!("{base}/start")).body("x"))
+            .await
+            .unwrap();
+        assert_eq!(response.url().path(), "/done");
+        assert_eq!(response.text().await.unwrap(), "ok");
+        assert_eq!(
+            server.await.unwrap(),
+            ["POST /start HTTP/1.1", "GET /done HTTP/1.1"]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Password-based encryption of exported files.
+//!
+//! The container starts with a header carrying everything needed to derive the
+//! key again, followed by the file encrypted with AES-256-GCM in 64 KiB chunks
+//! using the STREAM construction, so truncation and reordering are detected.
+//!
+//! ```text
+//! "IXSIEENC" | version (1) | m_cost, t_cost, p_cost (u32 LE each) | salt (16) | nonce (7) | chunks...
+//! ```
+
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use aes_gcm::{
+    aead::{
+        rand_core::RngCore,
+        stream::{DecryptorBE32, EncryptorBE32},
+        OsRng,
+    },
+    Aes256Gcm, KeyInit,
+};
+use anyhow::{anyhow, bail};
+use argon2::{Algorithm, Argon2, Params, Version};
+use shared::{Locale, Text};
+
+/// Appended to the names of encrypted files
+pub const EXTENSION: &str = "enc";
+
+const MAGIC: &[u8; 8] = b"IXSIEENC";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 7;
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+/// Highest Argon2 costs accepted from a header: memory in KiB, passes and
+/// lanes. New files use the much lower defaults of the `argon2` crate, and a
+/// crafted file must not make decryption allocate gigabytes or run for hours.
+const MAX_COSTS: [u32; 3] = [256 * 1024, 16, 8];
+
+/// `path` with [`EXTENSION`] appended, e.g. `2023-04.pdf.enc`
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(EXTENSION);
+    PathBuf::from(path)
+}
+
+/// `path` without [`EXTENSION`], or `None` if it doesn't look encrypted
+pub fn decrypted_path(path: &Path) -> Option<PathBuf> {
+    (path.extension()? == EXTENSION).then(|| path.with_extension(""))
+}
+
+pub fn encrypt(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    passphrase: &str,
+) -> anyhow::Result<()> {
+    let params = Params::default();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    for cost in [params.m_cost(), params.t_cost(), params.p_cost()] {
+        writer.write_all(&cost.to_le_bytes())?;
+    }
+    writer.write_all(&salt)?;
+    writer.write_all(&nonce)?;
+
+    let cipher = cipher(passphrase, &salt, params)?;
+    let mut encryptor = EncryptorBE32::from_aead(cipher, nonce.as_ref().into());
+    let mut chunk = read_chunk(&mut reader, CHUNK_LEN)?;
+    loop {
+        let next = read_chunk(&mut reader, CHUNK_LEN)?;
+        if next.is_empty() {
+            let ciphertext = encryptor
+                .encrypt_last(chunk.as_slice())
+                .map_err(|_| anyhow!(Text::EncryptionFailed.get(Locale::current())))?;
+            writer.write_all(&ciphertext)?;
+            break;
+        }
+        let ciphertext = encryptor
+            .encrypt_next(chunk.as_slice())
+            .map_err(|_| anyhow!(Text::EncryptionFailed.get(Locale::current())))?;
+        writer.write_all(&ciphertext)?;
+        chunk = next;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn decrypt(
+    mut reader: impl Read,
+    mut writer: impl Write,
+    passphrase: &str,
+) -> anyhow::Result<()> {
+    let mut header = [0; MAGIC.len() + 1 + 12 + SALT_LEN + NONCE_LEN];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| not_encrypted())?;
+    let (magic, rest) = header.split_at(MAGIC.len());
+    if magic != MAGIC {
+        return Err(not_encrypted());
+    }
+    let (version, rest) = rest.split_at(1);
+    if version[0] != VERSION {
+        bail!(Text::UnsupportedVersion.fill(Locale::current(), &[&version[0]]));
+    }
+    let (costs, rest) = rest.split_at(12);
+    let cost = |i: usize| u32::from_le_bytes(costs[i * 4..i * 4 + 4].try_into().unwrap());
+    let broken =
+        |err: &dyn std::fmt::Display| anyhow!(Text::BrokenHeader.fill(Locale::current(), &[err]));
+    if let Some(i) = (0..3).find(|&i| cost(i) > MAX_COSTS[i]) {
+        let limit = Text::CostTooHigh.fill(Locale::current(), &[&cost(i), &MAX_COSTS[i]]);
+        return Err(broken(&limit));
+    }
+    let params = Params::new(cost(0), cost(1), cost(2), None).map_err(|err| broken(&err))?;
+    let (salt, nonce) = rest.split_at(SALT_LEN);
+
+    let cipher = cipher(passphrase, salt, params)?;
+    let mut decryptor = DecryptorBE32::from_aead(cipher, nonce.into());
+    let failed = || anyhow!(Text::DecryptionFailed.get(Locale::current()));
+    let mut chunk = read_chunk(&mut reader, CHUNK_LEN + TAG_LEN)?;
+    loop {
+        let next = read_chunk(&mut reader, CHUNK_LEN + TAG_LEN)?;
+        if next.is_empty() {
+            let plaintext = decryptor
+                .decrypt_last(chunk.as_slice())
+                .map_err(|_| failed())?;
+            writer.write_all(&plaintext)?;
+            break;
+        }
+        let plaintext = decryptor
+            .decrypt_next(chunk.as_slice())
+            .map_err(|_| failed())?;
+        writer.write_all(&plaintext)?;
+        chunk = next;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn not_encrypted() -> anyhow::Error {
+    anyhow!(Text::NotEncrypted.get(Locale::current()))
+}
+
+fn cipher(passphrase: &str, salt: &[u8], params: Params) -> anyhow::Result<Aes256Gcm> {
+    let mut key = [0; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow!(Text::KeyDerivationFailed.fill(Locale::current(), &[&err])))?;
+    Ok(Aes256Gcm::new(&key.into()))
+}
+
+/// Reads up to `len` bytes, returning fewer only at the end of the input.
+fn read_chunk(reader: &mut impl Read, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut chunk)?;
+    Ok(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(len: usize) {
+        let plaintext = (0..len).map(|i| i as u8).collect::<Vec<_>>();
+        let mut encrypted = Vec::new();
+        encrypt(plaintext.as_slice(), &mut encrypted, "passphrase").unwrap();
+        let mut decrypted = Vec::new();
+        decrypt(encrypted.as_slice(), &mut decrypted, "passphrase").unwrap();
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn round_trips_across_chunk_boundaries() {
+        for len in [0, 1, CHUNK_LEN, CHUNK_LEN + 1, 3 * CHUNK_LEN] {
+            round_trip(len);
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_passphrase_and_truncation() {
+        let plaintext = vec![0; 2 * CHUNK_LEN];
+        let mut encrypted = Vec::new();
+        encrypt(plaintext.as_slice(), &mut encrypted, "passphrase").unwrap();
+        assert!(decrypt(encrypted.as_slice(), std::io::sink(), "wrong").is_err());
+        let truncated = &encrypted[..encrypted.len() - CHUNK_LEN - TAG_LEN];
+        assert!(decrypt(truncated, std::io::sink(), "passphrase").is_err());
+    }
+
+    #[test]
+    fn rejects_excessive_costs() {
+        let mut encrypted = Vec::new();
+        encrypt(&b"%PDF"[..], &mut encrypted, "passphrase").unwrap();
+        let m_cost = MAGIC.len() + 1;
+        encrypted[m_cost..m_cost + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = decrypt(encrypted.as_slice(), std::io::sink(), "passphrase").unwrap_err();
+        assert!(err.to_string().contains(&u32::MAX.to_string()), "{err}");
+    }
+
+    #[test]
+    fn encrypted_paths() {
+        let path = encrypted_path(Path::new("dir/2023-04.pdf"));
+        assert_eq!(path, Path::new("dir/2023-04.pdf.enc"));
+        assert_eq!(decrypted_path(&path).unwrap(), Path::new("dir/2023-04.pdf"));
+        assert_eq!(decrypted_path(Path::new("2023-04.pdf")), None);
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context};
+use shared::{Locale, Text, YearMonth};
+
+use crate::{
+    report::{REPORT_JSON, REPORT_MARKDOWN},
+    settings::SettingsStore,
+};
+
+/// Save location of the last export from the window that saved anything. The
+/// webview may only open exported files inside it or the save location of
+/// scheduled exports.
+#[derive(Default)]
+pub struct LastSaveLocation(pub Mutex<Option<PathBuf>>);
+
+impl LastSaveLocation {
+    /// Folders the webview may open files in: this save location, then the
+    /// one of scheduled exports
+    pub fn roots(&self, store: &SettingsStore) -> Vec<PathBuf> {
+        let last = self.0.lock().unwrap().clone();
+        last.into_iter().chain(store.get().save_location).collect()
+    }
+}
+
+/// Opens a file or folder with the default application of the OS.
+pub fn open(path: &Path) -> anyhow::Result<()> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    tracing::debug!(path = %path.display(), program, "opening");
+    // explorer exits with 1 even on success, so only spawning is checked
+    let mut child = Command::new(program)
+        .arg(path)
+        .spawn()
+        .with_context(|| Text::CannotOpen.fill(Locale::current(), &[&path.display()]))?;
+    // Waited for in the background so that it doesn't linger as a zombie
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Opens `path` if it is a file an export wrote inside one of `roots`
+pub fn open_inside(roots: &[PathBuf], path: &Path) -> anyhow::Result<()> {
+    let path = inside(roots, path)?;
+    if !path.is_file() || !is_exported(&path) {
+        bail!(Text::NotExported.fill(Locale::current(), &[&path.display()]));
+    }
+    open(&path)
+}
+
+/// Whether `path` is named like a file of an export, possibly encrypted: the
+/// PDF of a month such as `2023-04.pdf` or `2023-04 (1).pdf`, an
+/// `ixsie-*.zip` or `ixsie-*.tar.gz` archive, or the report
+fn is_exported(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let name = name.strip_suffix(".enc").unwrap_or(name);
+    if let Some(stem) = name.strip_suffix(".pdf") {
+        let Some((month, rest)) = stem.get(..7).zip(stem.get(7..)) else {
+            return false;
+        };
+        let numbered = rest
+            .strip_prefix(" (")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        return YearMonth::from_str(month).is_ok() && (rest.is_empty() || numbered);
+    }
+    let archive =
+        name.starts_with("ixsie-") && (name.ends_with(".zip") || name.ends_with(".tar.gz"));
+    archive || name == REPORT_JSON || name == REPORT_MARKDOWN
+}
+
+/// Opens the first of `roots` that still exists
+pub fn open_root(roots: &[PathBuf]) -> anyhow::Result<()> {
+    match roots.iter().find(|root| root.is_dir()) {
+        Some(root) => open(root),
+        None => bail!(Text::NoSaveLocation.get(Locale::current())),
+    }
+}
+
+/// `path` with symbolic links and `..` resolved, if that is inside one of
+/// `roots`
+pub fn inside(roots: &[PathBuf], path: &Path) -> anyhow::Result<PathBuf> {
+    let locale = Locale::current();
+    let canonical = path
+        .canonicalize()
+        .with_context(|| Text::CannotOpen.fill(locale, &[&path.display()]))?;
+    if !roots.iter().any(|root| {
+        root.canonicalize()
+            .is_ok_and(|root| canonical.starts_with(root))
+    }) {
+        bail!(Text::OutsideSaveLocation.fill(locale, &[&path.display()]));
+    }
+    Ok(canonical)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_paths_inside_the_root_are_allowed() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("exports");
+        let scheduled = dir.path().join("scheduled");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::create_dir(&scheduled).unwrap();
+        let pdf = root.join("2023-04.pdf");
+        std::fs::write(&pdf, b"%PDF").unwrap();
+        let scheduled_pdf = scheduled.join("2023-05.pdf");
+        std::fs::write(&scheduled_pdf, b"%PDF").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), b"").unwrap();
+        let roots = [root.clone(), scheduled];
+
+        assert_eq!(inside(&roots, &pdf).unwrap(), pdf.canonicalize().unwrap());
+        assert!(inside(&roots, &scheduled_pdf).is_ok());
+        assert!(inside(&roots[..1], &scheduled_pdf).is_err());
+        assert!(inside(&roots, &root.join("../secret.txt")).is_err());
+        assert!(inside(&roots, &root.join("missing.pdf")).is_err());
+        assert!(inside(&roots, dir.path()).is_err());
+        assert!(inside(&[], &pdf).is_err());
+    }
+
+    #[test]
+    fn only_exported_files_are_opened() {
+        for name in [
+            "2023-04.pdf",
+            "2023-04 (1).pdf",
+            "2023-04.pdf.enc",
+            "ixsie-2018-04_2024-03.zip",
+            "ixsie-2018-04_2024-03.tar.gz.enc",
+            "ixsie-report.json",
+            "ixsie-report.md",
+        ] {
+            assert!(is_exported(Path::new(name)), "{name}");
+        }
+        for name in [
+            "setup.exe",
+            "run.bat",
+            "start.command",
+            "app.desktop",
+            "2023-04.exe",
+            "notes.pdf",
+            "2023-04 (x).pdf",
+            "連絡帳.pdf",
+            "archive.zip",
+            "ixsie-report.exe",
+        ] {
+            assert!(!is_exported(Path::new(name)), "{name}");
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let installer = dir.path().join("setup.exe");
+        std::fs::write(&installer, b"MZ").unwrap();
+        let roots = [dir.path().to_path_buf()];
+        assert!(open_inside(&roots, &installer).is_err());
+    }
+}
@@ -0,0 +1,1090 @@
+use std::{
+    future::Future,
+    marker::Unpin,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, Local};
+use futures::{future::BoxFuture, stream::StreamExt};
+use reqwest::{Client, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use shared::{
+    Credentials, FileAction, Locale, LoginStep, Message, MonthReport, NetworkSettings,
+    OutputFormat, OverwritePolicy, Plan, PlanAction, PlannedFile, PlannedMonth, RunReport,
+    SinkConfig, Text, YearMonth, YearMonthRange, MAX_MAINTENANCE_WAIT_MINS,
+};
+use tauri::{AppHandle, Manager, Window};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::{oneshot, Mutex},
+};
+
+use crate::{
+    archive::ArchiveWriter,
+    client, crypto, logging,
+    login::{self, SessionExpired},
+    maintenance::{self, Unavailable},
+    report,
+    sink::{self, Sink},
+};
+
+/// Destination of the progress messages of an export
+pub trait Emitter {
+    fn emit(&self, message: Message) -> anyhow::Result<()>;
+
+    /// Asks the user for the input of an extra sign-in step. Unattended
+    /// exports can't answer.
+    fn ask(&self, _step: LoginStep) -> BoxFuture<'_, anyhow::Result<String>> {
+        Box::pin(async { bail!(Text::VerificationUnattended.get(Locale::current())) })
+    }
+}
+
+impl Emitter for Window {
+    fn emit(&self, message: Message) -> anyhow::Result<()> {
+        self.emit_all("output", message)?;
+        Ok(())
+    }
+
+    /// Shows the step with a `login_step` event and waits for
+    /// `submit_login_step`
+    fn ask(&self, step: LoginStep) -> BoxFuture<'_, anyhow::Result<String>> {
+        Box::pin(async move {
+            let (sender, receiver) = oneshot::channel();
+            *self.state::<PendingLoginStep>().0.lock().await = Some(sender);
+            self.emit_all("login_step", step)?;
+            receiver
+                .await
+                .ok()
+                .flatten()
+                .context(Text::VerificationCancelled.get(Locale::current()))
+        })
+    }
+}
+
+impl Emitter for AppHandle {
+    fn emit(&self, message: Message) -> anyhow::Result<()> {
+        self.emit_all("output", message)?;
+        Ok(())
+    }
+}
+
+/// Held while an export is running so that manual and scheduled exports don't
+/// write to the same files at the same time.
+#[derive(Default)]
+pub struct ExportLock(pub Mutex<()>);
+
+/// Where the answer to the sign-in step an export waits for goes. `None`
+/// cancels signing in.
+#[derive(Default)]
+pub struct PendingLoginStep(pub Mutex<Option<oneshot::Sender<Option<String>>>>);
+
+/// Where the exported files go
+pub struct ExportOptions {
+    pub save_location: PathBuf,
+    pub format: OutputFormat,
+    /// What to do with files that already exist
+    pub policy: OverwritePolicy,
+    /// Encrypts each file, or the archive, if set
+    pub passphrase: Option<String>,
+    /// Backup targets each file, or the archive, is copied to after it has
+    /// been written
+    pub sinks: Vec<SinkConfig>,
+    pub network: NetworkSettings,
+}
+
+impl ExportOptions {
+    /// The archive holding `range`, if the months are archived
+    fn archive_path(&self, range: &YearMonthRange) -> Option<PathBuf> {
+        let path = self.save_location.join(self.format.archive_name(range)?);
+        Some(self.encrypted(path))
+    }
+
+    /// Where `month` is written, or its entry name inside the archive
+    fn month_path(&self, month: YearMonth) -> PathBuf {
+        let name = format!("{month}.pdf");
+        match self.format {
+            OutputFormat::Files => self.encrypted(self.save_location.join(name)),
+            OutputFormat::Zip | OutputFormat::TarGz => PathBuf::from(name),
+        }
+    }
+
+    fn encrypted(&self, path: PathBuf) -> PathBuf {
+        match self.passphrase {
+            Some(_) => crypto::encrypted_path(&path),
+            None => path,
+        }
+    }
+
+    /// Applies the overwrite policy to a file about to be written
+    fn target(&self, path: PathBuf) -> Target {
+        if !path.exists() {
+            return Target::Create(path);
+        }
+        match self.policy {
+            OverwritePolicy::Overwrite => Target::Overwrite(path),
+            OverwritePolicy::Skip => Target::Skip(path),
+            OverwritePolicy::KeepBoth => Target::Rename(numbered(&path)),
+            OverwritePolicy::IfChanged => Target::Compare(path),
+        }
+    }
+}
+
+/// Where a file is written according to the overwrite policy
+enum Target {
+    Create(PathBuf),
+    Overwrite(PathBuf),
+    /// The file exists; write to this new name instead
+    Rename(PathBuf),
+    /// The file exists and is left alone
+    Skip(PathBuf),
+    /// The file exists and is replaced only if the download differs
+    Compare(PathBuf),
+}
+
+impl Target {
+    fn planned(self) -> (PathBuf, PlanAction) {
+        match self {
+            Target::Create(path) => (path, PlanAction::Download),
+            Target::Overwrite(path) => (path, PlanAction::Overwrite),
+            Target::Rename(path) => (path, PlanAction::KeepBoth),
+            Target::Skip(path) => (path, PlanAction::Skip),
+            Target::Compare(path) => (path, PlanAction::IfChanged),
+        }
+    }
+}
+
+/// The first of `name (1).ext`, `name (2).ext`, ... that doesn't exist yet
+fn numbered(path: &Path) -> PathBuf {
+    (1..)
+        .map(|n| path.with_file_name(with_infix(path, &format!(" ({n})"))))
+        .find(|path| !path.exists())
+        .expect("some number is free")
+}
+
+/// The file name of `path` with `infix` inserted before the extension.
+/// Everything after the first dot counts as the extension, so that `.pdf.enc`
+/// stays together.
+fn with_infix(path: &Path, infix: &str) -> String {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (stem, extension) = name.split_at(name.find('.').unwrap_or(name.len()));
+    format!("{stem}{infix}{extension}")
+}
+
+/// Folder in the save location that replaced files are kept in
+const VERSIONS_DIR: &str = "versions";
+
+/// Copies `path` into the versions folder, named after the time it was
+/// written, e.g. `versions/2023-04_20240315-093000.pdf`, and numbered if
+/// another version was written in the same second.
+async fn keep_version(path: &Path, save_location: &Path) -> anyhow::Result<PathBuf> {
+    let modified: DateTime<Local> = tokio::fs::metadata(path).await?.modified()?.into();
+    let dir = save_location.join(VERSIONS_DIR);
+    tokio::fs::create_dir_all(&dir).await?;
+    let version = dir.join(with_infix(
+        path,
+        &modified.format("_%Y%m%d-%H%M%S").to_string(),
+    ));
+    let version = match version.exists() {
+        true => numbered(&version),
+        false => version,
+    };
+    tokio::fs::copy(path, &version).await?;
+    tracing::info!(from = %path.display(), to = %version.display(), "kept the previous version");
+    Ok(version)
+}
+
+/// Outcome of exporting one month
+struct Written {
+    path: PathBuf,
+    action: Option<FileAction>,
+    /// `None` if the month was skipped
+    downloaded: Option<Downloaded>,
+    /// Checksum of the previous contents, if known
+    stored_sha256: Option<String>,
+    /// Where the replaced file was kept
+    previous_version: Option<PathBuf>,
+}
+
+impl Written {
+    fn new(
+        path: PathBuf,
+        action: Option<FileAction>,
+        downloaded: Option<Downloaded>,
+        stored_sha256: Option<String>,
+    ) -> Self {
+        Self {
+            path,
+            action,
+            downloaded,
+            stored_sha256,
+            previous_version: None,
+        }
+    }
+}
+
+pub async fn start_body(
+    emitter: &impl Emitter,
+    creds: Credentials,
+    months: Vec<YearMonth>,
+    options: ExportOptions,
+) -> anyhow::Result<RunReport> {
+    tracing::info!(
+        months = months.len(),
+        format = ?options.format,
+        policy = ?options.policy,
+        encrypted = options.passphrase.is_some(),
+        sinks = options.sinks.len(),
+        save_location = %options.save_location.display(),
+        "starting export"
+    );
+    let client = Arc::new(client::build(&options.network)?);
+    let session = Session {
+        client: &client,
+        creds: &creds,
+        deadline: maintenance_deadline(&options.network),
+        renewed: Mutex::new(None),
+    };
+    login(emitter, &client, &creds, session.deadline).await?;
+    let sinks = sink::from_configs(options.sinks.clone(), &client);
+    download_concurrently(
+        emitter,
+        Arc::clone(&client),
+        months,
+        &options,
+        &sinks,
+        &session,
+    )
+    .await
+}
+
+/// What is needed to sign in again when the session of an export expires
+struct Session<'a> {
+    client: &'a Client,
+    creds: &'a Credentials,
+    /// See [`maintenance_deadline`]
+    deadline: Option<Instant>,
+    /// Outcome of signing in again, which is done only once per export
+    renewed: Mutex<Option<Result<(), String>>>,
+}
+
+impl Session<'_> {
+    /// Signs in again unless that has already been done, in which case its
+    /// outcome is returned, so that months failing at the same time share
+    /// one new session.
+    async fn renew(&self, emitter: &impl Emitter) -> anyhow::Result<()> {
+        let mut renewed = self.renewed.lock().await;
+        if renewed.is_none() {
+            tracing::warn!("session expired");
+            emitter.emit(Message::message(
+                Text::SigningInAgain.get(Locale::current()).into(),
+            ))?;
+            let result = login(emitter, self.client, self.creds, self.deadline).await;
+            *renewed = Some(result.map_err(|err| format!("{err:#}")));
+        }
+        match &*renewed {
+            Some(Err(err)) => Err(anyhow::Error::msg(err.clone())),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Until when an export started now may wait for maintenance to end, at
+/// most [`MAX_MAINTENANCE_WAIT_MINS`] whatever the settings say
+fn maintenance_deadline(network: &NetworkSettings) -> Option<Instant> {
+    let mins = network
+        .maintenance_wait_mins?
+        .min(MAX_MAINTENANCE_WAIT_MINS);
+    Instant::now().checked_add(Duration::from_secs(mins.checked_mul(60)?))
+}
+
+/// Logs in, trying again once ixsie is back if it is under maintenance
+async fn login(
+    emitter: &impl Emitter,
+    client: &Client,
+    creds: &Credentials,
+    deadline: Option<Instant>,
+) -> anyhow::Result<()> {
+    let locale = Locale::current();
+    let base = Url::parse(login::BASE_URL)?;
+    loop {
+        emitter.emit(Message::message(Text::LoggingIn.get(locale).into()))?;
+        tracing::info!(email = %logging::redact_email(&creds.email), "logging in");
+        match login::sign_in(client, &base, creds, emitter).await {
+            Ok(()) => break,
+            Err(err) => {
+                tracing::warn!(error = %format!("{err:#}"), "login failed");
+                if !wait_for_maintenance(emitter, &err, deadline).await? {
+                    return Err(err);
+                }
+            }
+        }
+    }
+    tracing::info!("logged in");
+    emitter.emit(Message::message(Text::LoggedIn.get(locale).into()))?;
+    Ok(())
+}
+
+/// Waits for ixsie to come back if `err` is [`Unavailable`] and `deadline`
+/// allows. Returns whether it waited, that is whether to try again.
+async fn wait_for_maintenance(
+    emitter: &impl Emitter,
+    err: &anyhow::Error,
+    deadline: Option<Instant>,
+) -> anyhow::Result<bool> {
+    let Some(unavailable) = err.downcast_ref::<Unavailable>() else {
+        return Ok(false);
+    };
+    let left = deadline.map_or(Duration::ZERO, |deadline| {
+        deadline.saturating_duration_since(Instant::now())
+    });
+    if left.is_zero() {
+        // The error itself tells about the maintenance unless waiting ran out
+        if deadline.is_some() {
+            emitter.emit(Message::Unavailable(None))?;
+        }
+        return Ok(false);
+    }
+    let delay = unavailable
+        .retry_after
+        .unwrap_or(maintenance::DEFAULT_RETRY_AFTER)
+        .max(RETRY_DELAY)
+        .min(left);
+    tracing::warn!(
+        delay_secs = delay.as_secs(),
+        "waiting for maintenance to end"
+    );
+    emitter.emit(Message::Unavailable(maintenance::resume_time(delay)))?;
+    tokio::time::sleep(delay).await;
+    Ok(true)
+}
+
+async fn download_concurrently(
+    emitter: &impl Emitter,
+    client: Arc<Client>,
+    months: Vec<YearMonth>,
+    options: &ExportOptions,
+    sinks: &[Sink],
+    session: &Session<'_>,
+) -> anyhow::Result<RunReport> {
+    let locale = Locale::current();
+    let save_location = options.save_location.as_path();
+    let passphrase = options.passphrase.clone();
+    let idle_timeout = options.network.idle_timeout_secs.map(Duration::from_secs);
+    let (months, range) = sorted_months(months)?;
+    let started_at = Local::now();
+    let total = months.len();
+    let previous = report::read(save_location).await;
+    let previous = previous.as_ref();
+    let (archive_path, archive_action) = match options
+        .archive_path(&range)
+        .map(|path| options.target(path))
+    {
+        None => (None, None),
+        Some(Target::Create(path)) => (Some(path), Some(FileAction::Created)),
+        // The manifest differs from run to run, so a new archive never
+        // matches the existing one
+        Some(Target::Overwrite(path) | Target::Compare(path)) => {
+            (Some(path), Some(FileAction::Overwritten))
+        }
+        Some(Target::Rename(path)) => (Some(path), Some(FileAction::KeptBoth)),
+        Some(Target::Skip(path)) => {
+            emitter.emit(Message::message(
+                Text::SkippedExisting.fill(locale, &[&path.display()]),
+            ))?;
+            let report = RunReport {
+                started_at,
+                finished_at: Local::now(),
+                range,
+                save_location: save_location.to_path_buf(),
+                months: months
+                    .into_iter()
+                    .map(|month| MonthReport {
+                        sha256: previous
+                            .and_then(|previous| previous.sha256(month))
+                            .map(str::to_string),
+                        ..MonthReport::skipped(month, path.clone(), Duration::ZERO)
+                    })
+                    .collect(),
+                archive: Some(path),
+                sinks: Vec::new(),
+                earlier: Vec::new(),
+            };
+            for month in &report.months {
+                emitter.emit(Message::Finished(month.clone()))?;
+            }
+            emitter.emit(Message::Report(report.clone()))?;
+            emitter.emit(Message::message(Text::Done.get(locale).into()))?;
+            return Ok(report);
+        }
+    };
+    let archive = archive_path
+        .clone()
+        .map(|path| ArchiveWriter::create(path, options.format, passphrase.clone()))
+        .transpose()?;
+    // Only loose files are encrypted one by one
+    let passphrase = passphrase.filter(|_| archive.is_none());
+    let passphrase = passphrase.as_deref();
+    // With an archive only the finished archive is backed up
+    let month_sinks = if archive.is_some() { &[] } else { sinks };
+    let archive_ref = archive.as_ref();
+    let mut stream = tokio_stream::iter(months)
+        .map(move |month| {
+            let url = generate_url(month);
+            let client = Arc::clone(&client);
+            async move {
+                let started = Instant::now();
+                let result = async {
+                    emitter.emit(Message::Started(month))?;
+                    let (client, url) = (&client, &url);
+                    let fetch = move || async move {
+                        let mut data = Vec::new();
+                        let downloaded = download(client, &mut data, url, idle_timeout).await?;
+                        anyhow::Ok((data, downloaded))
+                    };
+                    let stored_sha256 = previous
+                        .and_then(|previous| previous.sha256(month))
+                        .map(str::to_string);
+                    if let Some(archive) = archive_ref {
+                        let path = options.month_path(month);
+                        let (data, downloaded) = with_retry(emitter, month, session, fetch).await?;
+                        archive.add(path.display().to_string(), data).await?;
+                        return anyhow::Ok(Written::new(
+                            path,
+                            None,
+                            Some(downloaded),
+                            stored_sha256,
+                        ));
+                    }
+                    let (path, action) = match options.target(options.month_path(month)) {
+                        Target::Create(path) => (path, FileAction::Created),
+                        Target::Rename(path) => (path, FileAction::KeptBoth),
+                        Target::Skip(path) => {
+                            return Ok(Written::new(
+                                path,
+                                Some(FileAction::Skipped),
+                                None,
+                                stored_sha256,
+                            ))
+                        }
+                        Target::Overwrite(path) | Target::Compare(path) => {
+                            let (data, downloaded) =
+                                with_retry(emitter, month, session, fetch).await?;
+                            let stored_sha256 = match stored_sha256 {
+                                Some(sha256) => Some(sha256),
+                                None => file_sha256(path.clone(), passphrase.map(Into::into)).await,
+                            };
+                            let unchanged = stored_sha256.as_ref() == Some(&downloaded.sha256);
+                            if unchanged && options.policy == OverwritePolicy::IfChanged {
+                                let action = Some(FileAction::Unchanged);
+                                return Ok(Written::new(
+                                    path,
+                                    action,
+                                    Some(downloaded),
+                                    stored_sha256,
+                                ));
+                            }
+                            // Anything not known to be identical is kept before
+                            // it is replaced
+                            let previous_version = match unchanged {
+                                true => None,
+                                false => Some(keep_version(&path, save_location).await?),
+                            };
+                            write_file(path.clone(), data, passphrase.map(Into::into)).await?;
+                            let action = Some(FileAction::Overwritten);
+                            return Ok(Written {
+                                previous_version,
+                                ..Written::new(path, action, Some(downloaded), stored_sha256)
+                            });
+                        }
+                    };
+                    let output_path = &path;
+                    let downloaded = with_retry(emitter, month, session, move || {
+                        let passphrase = passphrase.map(Into::into);
+                        download_file(client, url, idle_timeout, output_path.clone(), passphrase)
+                    })
+                    .await?;
+                    anyhow::Ok(Written::new(
+                        path,
+                        Some(action),
+                        Some(downloaded),
+                        stored_sha256,
+                    ))
+                }
+                .await;
+                match result {
+                    Ok(Written {
+                        path,
+                        action,
+                        downloaded: None,
+                        stored_sha256,
+                        ..
+                    }) => MonthReport {
+                        action,
+                        // Carried over so that later runs can still detect changes
+                        sha256: stored_sha256,
+                        ..MonthReport::skipped(month, path, started.elapsed())
+                    },
+                    Ok(Written {
+                        path,
+                        action,
+                        downloaded: Some(downloaded),
+                        stored_sha256,
+                        previous_version,
+                    }) => {
+                        // Files that weren't written have been backed up before
+                        let sinks = match action {
+                            Some(FileAction::Unchanged) => Vec::new(),
+                            _ => {
+                                futures::future::join_all(
+                                    month_sinks.iter().map(|sink| sink.upload(&path)),
+                                )
+                                .await
+                            }
+                        };
+                        // A failed backup doesn't fail the month; it is only reported
+                        MonthReport {
+                            action,
+                            changed: stored_sha256
+                                .is_some_and(|sha256| sha256 != downloaded.sha256),
+                            previous_version,
+                            sinks,
+                            ..MonthReport::done(
+                                month,
+                                path,
+                                downloaded.size,
+                                downloaded.sha256,
+                                started.elapsed(),
+                            )
+                        }
+                    }
+                    Err(err) => {
+                        tracing::error!(%month, error = %format!("{err:#}"), "month failed");
+                        MonthReport::failed(month, format!("{err}"), started.elapsed())
+                    }
+                }
+            }
+        })
+        .buffer_unordered(4);
+    let mut months = Vec::with_capacity(total);
+    while let Some(report) = stream.next().await {
+        if report.error.is_none() {
+            tracing::info!(
+                month = %report.month,
+                path = ?report.path,
+                action = ?report.action,
+                size = report.size,
+                sha256 = report.sha256.as_deref(),
+                changed = report.changed,
+                duration_ms = report.duration_ms,
+                "month done"
+            );
+        }
+        emitter.emit(Message::Finished(report.clone()))?;
+        if let (Some(action), Some(path)) = (report.action, &report.path) {
+            emitter.emit(Message::Decision(report.month, action, path.clone()))?;
+        }
+        let message = match &report.error {
+            Some(err) => Message::Failed(report.month, err.clone()),
+            None => Message::from(report.month),
+        };
+        emitter.emit(message)?;
+        if report.changed {
+            emitter.emit(Message::message(
+                Text::ContentsChanged.fill(locale, &[&report.month]),
+            ))?;
+        }
+        if let Some(version) = &report.previous_version {
+            emitter.emit(Message::message(
+                Text::PreviousFileKept.fill(locale, &[&report.month, &version.display()]),
+            ))?;
+        }
+        for sink in report.failed_sinks() {
+            emitter.emit(Message::error(Text::MonthBackupFailed.fill(
+                locale,
+                &[
+                    &report.month,
+                    &sink.name,
+                    &sink.error.as_deref().unwrap_or_default(),
+                ],
+            )))?;
+        }
+        months.push(report);
+    }
+    drop(stream);
+    months.sort_by_key(|report| report.month);
+    let mut report = RunReport {
+        started_at,
+        finished_at: Local::now(),
+        range,
+        save_location: save_location.to_path_buf(),
+        archive: archive_path,
+        months,
+        sinks: Vec::new(),
+        earlier: Vec::new(),
+    };
+    if let Some(previous) = previous {
+        report.carry_over(previous);
+    }
+    if let (Some(archive), Some(path)) = (archive, &report.archive) {
+        // Any failure drops the writer unfinished, which leaves the previous
+        // archive as it is
+        let result = async {
+            if archive_action == Some(FileAction::Overwritten)
+                && report.changed_months().next().is_some()
+            {
+                let version = keep_version(path, save_location).await?;
+                emitter.emit(Message::message(
+                    Text::PreviousArchiveKept.fill(locale, &[&version.display()]),
+                ))?;
+            }
+            emitter.emit(Message::message(Text::CreatingArchive.get(locale).into()))?;
+            // The manifest
+            for (name, data) in report::render(&report)? {
+                archive.add(name.into(), data).await?;
+            }
+            archive.finish().await
+        }
+        .await;
+        if let Err(err) = result {
+            tracing::error!(path = %path.display(), error = %format!("{err:#}"), "archive failed");
+            bail!(Text::ArchiveFailed.fill(locale, &[&err]));
+        }
+        tracing::info!(path = %path.display(), action = ?archive_action, "archive written");
+        if let Some(action) = archive_action {
+            emitter.emit(Message::message(format!(
+                "{}: {}",
+                path.display(),
+                action.label(locale)
+            )))?;
+        }
+        report.sinks = futures::future::join_all(sinks.iter().map(|sink| sink.upload(path))).await;
+        for sink in report.sinks.iter().filter(|sink| sink.error.is_some()) {
+            emitter.emit(Message::error(Text::BackupFailed.fill(
+                locale,
+                &[&sink.name, &sink.error.as_deref().unwrap_or_default()],
+            )))?;
+        }
+    }
+    if let Err(err) = report::write(&report, save_location).await {
+        tracing::error!(error = %format!("{err:#}"), "failed to write the report");
+        emitter.emit(Message::error(Text::ReportFailed.fill(locale, &[&err])))?;
+    }
+    emitter.emit(Message::Report(report.clone()))?;
+    emitter.emit(Message::message(Text::Done.get(locale).into()))?;
+    Ok(report)
+}
+
+/// Sorts and deduplicates the requested months.
+fn sorted_months(mut months: Vec<YearMonth>) -> anyhow::Result<(Vec<YearMonth>, YearMonthRange)> {
+    months.sort();
+    months.dedup();
+    let (Some(&first), Some(&last)) = (months.first(), months.last()) else {
+        bail!(Text::NoMonths.get(Locale::current()));
+    };
+    Ok((months, YearMonthRange::new(first, last)))
+}
+
+/// Logs in and works out what [`start_body`] would do with the same arguments,
+/// without writing anything.
+pub async fn plan(
+    emitter: &impl Emitter,
+    creds: Credentials,
+    months: Vec<YearMonth>,
+    options: ExportOptions,
+) -> anyhow::Result<Plan> {
+    let client = client::build(&options.network)?;
+    login(
+        emitter,
+        &client,
+        &creds,
+        maintenance_deadline(&options.network),
+    )
+    .await?;
+    let (months, range) = sorted_months(months)?;
+    let previous = report::read(&options.save_location).await;
+    let previous_sha256 = |month| Some(previous.as_ref()?.sha256(month)?.to_string());
+    let archive = options.archive_path(&range).map(|path| {
+        let (path, action) = match options.target(path) {
+            // See `download_concurrently`
+            Target::Compare(path) => (path, PlanAction::Overwrite),
+            target => target.planned(),
+        };
+        PlannedFile { path, action }
+    });
+    let months = months
+        .into_iter()
+        .map(|month| {
+            let path = options.month_path(month);
+            let (path, action) = match &archive {
+                // Entries always go into a new archive, unless it is skipped
+                Some(archive) if archive.action == PlanAction::Skip => (path, PlanAction::Skip),
+                Some(_) => (path, PlanAction::Download),
+                None => options.target(path).planned(),
+            };
+            PlannedMonth {
+                month,
+                action,
+                path,
+                previous_sha256: previous_sha256(month),
+            }
+        })
+        .collect();
+    Ok(Plan {
+        range,
+        save_location: options.save_location,
+        archive,
+        months,
+    })
+}
+
+fn generate_url(month: YearMonth) -> String {
+    format!(
+        "{}/user/contact/pdf?contactYear={}&contactMonth={}",
+        login::BASE_URL,
+        month.year,
+        month.month.number_from_month()
+    )
+}
+
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Runs `f` again with a growing delay while it fails with a transient error,
+/// once ixsie is back while it is under maintenance, and once more after
+/// signing in again if the session has expired.
+async fn with_retry<T, F, Fut>(
+    emitter: &impl Emitter,
+    month: YearMonth,
+    session: &Session<'_>,
+    f: F,
+) -> anyhow::Result<T>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut attempt = 1;
+    let mut signed_in_again = false;
+    loop {
+        match f().await {
+            Err(err) if err.is::<Unavailable>() => {
+                if !wait_for_maintenance(emitter, &err, session.deadline).await? {
+                    return Err(err);
+                }
+            }
+            Err(err) if !signed_in_again && err.is::<SessionExpired>() => {
+                tracing::warn!(%month, "session expired");
+                session.renew(emitter).await?;
+                signed_in_again = true;
+            }
+            Err(err) if attempt < MAX_ATTEMPTS && is_transient(&err) => {
+                tracing::warn!(%month, attempt, error = %format!("{err:#}"), "retrying");
+                emitter.emit(Message::message(Text::Retrying.fill(
+                    Locale::current(),
+                    &[&month, &err, &(attempt + 1), &MAX_ATTEMPTS],
+                )))?;
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether retrying might help: stalls, timeouts, connection problems and
+/// server-side errors
+fn is_transient(err: &anyhow::Error) -> bool {
+    if err.is::<Stalled>() {
+        return true;
+    }
+    err.downcast_ref::<reqwest::Error>().is_some_and(|err| {
+        err.is_timeout()
+            || err.is_connect()
+            || err.is_body()
+            || err.status().is_some_and(|status| {
+                status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+            })
+    })
+}
+
+/// No bytes were received for the given duration
+#[derive(Debug)]
+struct Stalled(Duration);
+
+impl std::fmt::Display for Stalled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&Text::Stalled.fill(Locale::current(), &[&self.0.as_secs()]))
+    }
+}
+
+impl std::error::Error for Stalled {}
+
+async fn unless_stalled<T>(
+    idle_timeout: Option<Duration>,
+    future: impl Future<Output = T>,
+) -> Result<T, Stalled> {
+    match idle_timeout {
+        Some(limit) => tokio::time::timeout(limit, future)
+            .await
+            .map_err(|_| Stalled(limit)),
+        None => Ok(future.await),
+    }
+}
+
+/// Downloads `url` into `path`, encrypted if a passphrase is given. The file
+/// is only written once the download has succeeded, so that a failed month
+/// leaves no partial file that the next run would skip.
+async fn download_file(
+    client: &Client,
+    url: &str,
+    idle_timeout: Option<Duration>,
+    path: PathBuf,
+    passphrase: Option<String>,
+) -> anyhow::Result<Downloaded> {
+    let mut data = Vec::new();
+    let downloaded = download(client, &mut data, url, idle_timeout).await?;
+    write_file(path, data, passphrase).await?;
+    Ok(downloaded)
+}
+
+/// Writes `data` into `path`, encrypted if a passphrase is given, leaving
+/// nothing behind on failure.
+pub async fn write_file(
+    path: PathBuf,
+    data: Vec<u8>,
+    passphrase: Option<String>,
+) -> anyhow::Result<()> {
+    tracing::debug!(path = %path.display(), encrypted = passphrase.is_some(), "writing");
+    tokio::task::spawn_blocking(move || {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut temp = tempfile::NamedTempFile::new_in(dir)?;
+        match passphrase {
+            Some(passphrase) => crypto::encrypt(
+                data.as_slice(),
+                std::io::BufWriter::new(temp.as_file_mut()),
+                &passphrase,
+            )?,
+            None => std::io::Write::write_all(temp.as_file_mut(), &data)?,
+        }
+        temp.persist(&path)?;
+        Ok(())
+    })
+    .await?
+}
+
+/// Checksum of the contents of an existing file, decrypted with `passphrase`
+/// if given. `None` if it can't be read.
+async fn file_sha256(path: PathBuf, passphrase: Option<String>) -> Option<String> {
+    tokio::task::spawn_blocking(move || {
+        let mut reader = std::io::BufReader::new(std::fs::File::open(path).ok()?);
+        let mut hasher = Sha256::new();
+        match passphrase {
+            Some(passphrase) => crypto::decrypt(reader, &mut hasher, &passphrase).ok()?,
+            None => {
+                std::io::copy(&mut reader, &mut hasher).ok()?;
+            }
+        }
+        Some(format!("{:x}", hasher.finalize()))
+    })
+    .await
+    .ok()?
+}
+
+/// Size and checksum of a downloaded file
+struct Downloaded {
+    size: u64,
+    sha256: String,
+}
+
+/// Downloads `url` into `writer`, giving up if no bytes arrive for
+/// `idle_timeout`. Web pages such as maintenance notices or the sign-in page
+/// are rejected rather than written.
+async fn download(
+    client: &Client,
+    mut writer: impl AsyncWrite + Unpin,
+    url: &str,
+    idle_timeout: Option<Duration>,
+) -> anyhow::Result<Downloaded> {
+    tracing::debug!(url, "downloading");
+    let response = unless_stalled(idle_timeout, client::send(client, client.get(url))).await??;
+    tracing::debug!(url, status = %response.status(), "response");
+    maintenance::check(&response)?;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        return Err(SessionExpired.into());
+    }
+    let mut response = response.error_for_status()?;
+    if is_html(&response) {
+        let final_url = response.url().clone();
+        let body = unless_stalled(idle_timeout, response.text()).await??;
+        if login::is_signin_page(&final_url, &body) {
+            return Err(SessionExpired.into());
+        }
+        if maintenance::is_maintenance_page(&body) {
+            return Err(Unavailable::default().into());
+        }
+        bail!(Text::NotPdf.get(Locale::current()));
+    }
+    let mut hasher = Sha256::new();
+    let mut size = 0;
+    while let Some(chunk) = unless_stalled(idle_timeout, response.chunk()).await?? {
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+        writer.write_all(&chunk).await?;
+    }
+    writer.flush().await?;
+    Ok(Downloaded {
+        size,
+        sha256: format!("{:x}", hasher.finalize()),
+    })
+}
+
+fn is_html(response: &reqwest::Response) -> bool {
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.trim_start().starts_with("text/html"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn stalled_download_is_aborted_as_transient() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/contact.pdf", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            // Promise a body but never send all of it
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\npartial")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+
+        let err = download(
+            &Client::new(),
+            tokio::io::sink(),
+            &url,
+            Some(Duration::from_millis(200)),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(err.is::<Stalled>());
+        assert!(is_transient(&err));
+    }
+
+    /// Answers one request with an HTML page and returns the URL to request
+    async fn serve_html_once(body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/contact.pdf", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/html; charset=utf-8\r\ncontent-length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn maintenance_page_is_not_saved_as_pdf() {
+        let url = serve_html_once("<h1>ただいまメンテナンス中です</h1>").await;
+        let mut written = Vec::new();
+        let err = download(&Client::new(), &mut written, &url, None)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.downcast_ref(), Some(&Unavailable::default()));
+        assert!(written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn signin_page_means_the_session_expired() {
+        let url = serve_html_once(
+            r#"<form method="post"><input name="loginId"><input type="password" name="password"></form>"#,
+        )
+        .await;
+        let err = download(&Client::new(), tokio::io::sink(), &url, None)
+            .await
+            .err()
+            .unwrap();
+        assert!(err.is::<SessionExpired>());
+    }
+
+    #[tokio::test]
+    async fn failed_download_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2023-04.pdf");
+        let url = serve_html_once("<h1>ただいまメンテナンス中です</h1>").await;
+        assert!(
+            download_file(&Client::new(), &url, None, path.clone(), None)
+                .await
+                .is_err()
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn numbered_keeps_the_whole_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2023-04.pdf.enc");
+        assert_eq!(numbered(&path), dir.path().join("2023-04 (1).pdf.enc"));
+        std::fs::write(dir.path().join("2023-04 (1).pdf.enc"), b"").unwrap();
+        assert_eq!(numbered(&path), dir.path().join("2023-04 (2).pdf.enc"));
+    }
+
+    #[tokio::test]
+    async fn checksum_of_encrypted_file_is_of_its_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2023-04.pdf.enc");
+        write_file(path.clone(), b"%PDF".to_vec(), Some("secret".into()))
+            .await
+            .unwrap();
+        let expected = format!("{:x}", Sha256::digest(b"%PDF"));
+        assert_eq!(
+            file_sha256(path.clone(), Some("secret".into())).await,
+            Some(expected)
+        );
+        assert_eq!(file_sha256(path, Some("wrong".into())).await, None);
+    }
+
+    #[tokio::test]
+    async fn replaced_file_is_kept_with_its_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("2023-04.pdf.enc");
+        std::fs::write(&path, b"old").unwrap();
+        let version = keep_version(&path, dir.path()).await.unwrap();
+        assert_eq!(version.parent().unwrap(), dir.path().join(VERSIONS_DIR));
+        let name = version.file_name().unwrap().to_string_lossy();
+        assert!(name.starts_with("2023-04_") && name.ends_with(".pdf.enc"));
+        assert_eq!(std::fs::read(&version).unwrap(), b"old");
+
+        // Kept again within the same second
+        std::fs::write(&path, b"new").unwrap();
+        let modified = std::fs::metadata(&version).unwrap().modified().unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let second = keep_version(&path, dir.path()).await.unwrap();
+        assert_ne!(second, version);
+        assert_eq!(std::fs::read(version).unwrap(), b"old");
+        assert_eq!(std::fs::read(second).unwrap(), b"new");
+    }
+}
@@ -0,0 +1,121 @@
+//! Diagnostic log written to daily files in the app data directory.
+//!
+//! Passwords, passphrases and secret keys are never passed to the log, and
+//! email addresses are masked with [`redact_email`].
+
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const FILE_PREFIX: &str = "ixsie-exporter";
+const FILE_SUFFIX: &str = "log";
+/// Number of daily files kept
+const MAX_FILES: usize = 7;
+
+/// Folder the log files are written to
+pub struct LogDir(PathBuf);
+
+/// Starts writing the log to `dir`. `RUST_LOG` overrides the default levels.
+pub fn init(dir: &Path) -> anyhow::Result<LogDir> {
+    fs::create_dir_all(dir)?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(FILE_PREFIX)
+        .filename_suffix(FILE_SUFFIX)
+        .max_log_files(MAX_FILES)
+        .build(dir)?;
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new("info,ixsie_exporter=debug"));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_ansi(false).with_writer(appender))
+        .try_init()?;
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        tracing::error!("{info}");
+        default_hook(info);
+    }));
+    tracing::info!(
+        version = env!("CARGO_PKG_VERSION"),
+        os = std::env::consts::OS,
+        "started"
+    );
+    Ok(LogDir(dir.to_path_buf()))
+}
+
+impl LogDir {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The log files, oldest first
+    pub fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let mut files = fs::read_dir(&self.0)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        name.starts_with(FILE_PREFIX) && name.ends_with(FILE_SUFFIX)
+                    })
+            })
+            .collect::<Vec<_>>();
+        // The names end with the date
+        files.sort();
+        Ok(files)
+    }
+
+    /// Concatenates the log files into a single file in `dir`.
+    pub fn export(&self, dir: &Path) -> anyhow::Result<PathBuf> {
+        let path = dir.join(format!(
+            "{FILE_PREFIX}-{}.{FILE_SUFFIX}",
+            Local::now().format("%Y%m%d-%H%M%S")
+        ));
+        let mut output = BufWriter::new(File::create(&path)?);
+        for file in self.files()? {
+            io::copy(&mut File::open(file)?, &mut output)?;
+        }
+        output.flush()?;
+        Ok(path)
+    }
+}
+
+/// Masks the local part of an email address, e.g. `t***@example.com`.
+pub fn redact_email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            let first = local.chars().next().map(String::from).unwrap_or_default();
+            format!("{first}***@{domain}")
+        }
+        None => "***".into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emails_are_masked() {
+        assert_eq!(redact_email("taro@example.com"), "t***@example.com");
+        assert_eq!(redact_email("taro"), "***");
+    }
+
+    #[test]
+    fn export_concatenates_the_files_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = LogDir(dir.path().join("logs"));
+        fs::create_dir(logs.path()).unwrap();
+        fs::write(logs.path().join("ixsie-exporter.2024-03-16.log"), "b\n").unwrap();
+        fs::write(logs.path().join("ixsie-exporter.2024-03-15.log"), "a\n").unwrap();
+        fs::write(logs.path().join("other.txt"), "x\n").unwrap();
+        let exported = logs.export(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(exported).unwrap(), "a\nb\n");
+    }
+}
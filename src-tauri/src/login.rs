@@ -0,0 +1,523 @@
+//! Signing in to ixsie the way a browser does: the sign-in page is fetched
+//! first so that the session cookie and any hidden tokens of the form are
+//! sent back with the credentials.
+
+use std::{fmt, sync::OnceLock};
+
+use regex::Regex;
+use reqwest::{multipart, Client, Response, StatusCode, Url};
+use shared::{Credentials, Locale, LoginStep, Text};
+
+use crate::{
+    client,
+    export::Emitter,
+    maintenance::{self, Unavailable},
+};
+
+pub const BASE_URL: &str = "https://app.ixsie.jp";
+const SIGNIN_PATH: &str = "/signin";
+
+/// Why signing in failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginError {
+    InvalidCredentials,
+    AccountLocked,
+    /// The hidden token of the form was rejected, e.g. because it expired
+    FormExpired,
+    /// The sign-in page has no recognizable form
+    FormNotFound,
+    /// Verification went on for more than [`MAX_STEPS`] steps
+    TooManySteps,
+    /// The page after the last step is neither a form nor signed in to
+    Unconfirmed,
+    UnexpectedStatus(StatusCode),
+}
+
+impl fmt::Display for LoginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locale = Locale::current();
+        let message = match self {
+            LoginError::InvalidCredentials => Text::LoginFailed.get(locale).into(),
+            LoginError::AccountLocked => Text::AccountLocked.get(locale).into(),
+            LoginError::FormExpired => Text::LoginFormExpired.get(locale).into(),
+            LoginError::FormNotFound => Text::SigninFormNotFound.get(locale).into(),
+            LoginError::TooManySteps => Text::TooManyLoginSteps.get(locale).into(),
+            LoginError::Unconfirmed => Text::SigninUnconfirmed.get(locale).into(),
+            LoginError::UnexpectedStatus(status) => {
+                Text::LoginUnexpectedStatus.fill(locale, &[status])
+            }
+        };
+        f.write_str(&message)
+    }
+}
+
+impl std::error::Error for LoginError {}
+
+/// The session ended, so ixsie answered with the sign-in page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionExpired;
+
+impl fmt::Display for SessionExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(Text::SessionExpired.get(Locale::current()))
+    }
+}
+
+impl std::error::Error for SessionExpired {}
+
+/// Whether ixsie answered a request at `url` with the sign-in page instead
+pub fn is_signin_page(url: &Url, html: &str) -> bool {
+    url.path() == SIGNIN_PATH || SigninForm::parse(html).is_some()
+}
+
+const SIGNOUT_WORDS: &[&str] = &["ログアウト", "log out", "logout", "sign out"];
+
+/// Whether `html` at `url` is a page of a signed-in session, which links to
+/// signing out or at least offers it
+fn is_signed_in_page(url: &Url, html: &str) -> bool {
+    static SIGNOUT: OnceLock<Regex> = OnceLock::new();
+    let signout = SIGNOUT.get_or_init(|| {
+        Regex::new(r#"(?i)\b(?:href|action)\s*=\s*["']?[^"'\s>]*/(?:signout|logout)\b"#).unwrap()
+    });
+    !url.path().starts_with(SIGNIN_PATH)
+        && (signout.is_match(html) || mentions(&visible_text(html), SIGNOUT_WORDS))
+}
+
+/// Signs in to the ixsie instance at `base` with the cookie store of
+/// `client`, asking `emitter` for the input of any extra steps such as a code
+/// sent by email. Failures the user can act on are [`LoginError`]s, and
+/// [`Unavailable`] while ixsie is under maintenance.
+pub async fn sign_in(
+    client: &Client,
+    base: &Url,
+    creds: &Credentials,
+    emitter: &impl Emitter,
+) -> anyhow::Result<()> {
+    let signin_url = base.join(SIGNIN_PATH)?;
+    let response = client::send(client, client.get(signin_url)).await?;
+    maintenance::check(&response)?;
+    let page_url = response.url().clone();
+    let status = response.status();
+    let body = response.text().await?;
+    check_status(status, &page_url, &body)?;
+    let form = SigninForm::parse(&body).ok_or(LoginError::FormNotFound)?;
+    let mut fields = form.form.hidden.clone();
+    fields.push((form.email_field.clone(), creds.email.clone()));
+    fields.push((form.password_field.clone(), creds.password.clone()));
+    let mut response = submit(client, &page_url, &form.form, fields).await?;
+
+    // Each answer is either the page signed in to, the sign-in form again on
+    // failure, or a further step to fill in
+    for step in 1..=MAX_STEPS {
+        maintenance::check(&response)?;
+        let status = response.status();
+        let page_url = response.url().clone();
+        let body = response.text().await?;
+        check_status(status, &page_url, &body)?;
+        if SigninForm::parse(&body).is_some() {
+            let err = failure(&body);
+            tracing::warn!(%page_url, error = ?err, "sign-in rejected");
+            return Err(err);
+        }
+        let Some(form) = VerificationForm::parse(&body) else {
+            if !is_signed_in_page(&page_url, &body) {
+                tracing::warn!(%page_url, "unknown page after signing in");
+                return Err(LoginError::Unconfirmed.into());
+            }
+            tracing::debug!(%page_url, "signed in");
+            return Ok(());
+        };
+        tracing::info!(%page_url, step, "sign-in needs verification");
+        let message = match form.message.as_str() {
+            "" => Text::VerificationPrompt.get(Locale::current()).into(),
+            message => message.into(),
+        };
+        let answer = emitter.ask(LoginStep { message }).await?;
+        let mut fields = form.form.hidden.clone();
+        fields.push((form.code_field.clone(), answer.trim().to_string()));
+        response = submit(client, &page_url, &form.form, fields).await?;
+    }
+    Err(LoginError::TooManySteps.into())
+}
+
+/// Sends `fields` the way `form` on the page at `page_url` would
+async fn submit(
+    client: &Client,
+    page_url: &Url,
+    form: &Form,
+    fields: Vec<(String, String)>,
+) -> anyhow::Result<Response> {
+    let action = match &form.action {
+        Some(action) => page_url.join(action)?,
+        None => page_url.clone(),
+    };
+    tracing::debug!(
+        %action,
+        fields = fields.len(),
+        multipart = form.multipart,
+        "submitting form"
+    );
+    let request = client.post(action);
+    let request = if form.multipart {
+        let form = fields
+            .into_iter()
+            .fold(multipart::Form::new(), |form, (name, value)| {
+                form.text(name, value)
+            });
+        request.multipart(form)
+    } else {
+        request.form(&fields)
+    };
+    client::send(client, request).await
+}
+
+/// Checks a response other than a 503, which [`maintenance::check`] handles
+fn check_status(status: StatusCode, url: &Url, body: &str) -> anyhow::Result<()> {
+    match status {
+        // 419 is what Laravel answers to an expired CSRF token
+        StatusCode::FORBIDDEN => Err(LoginError::FormExpired.into()),
+        status if status.as_u16() == 419 => Err(LoginError::FormExpired.into()),
+        status if status.is_success() => {
+            // The sign-in page and the pages signed in to may announce
+            // upcoming maintenance, which isn't the maintenance page itself
+            if SigninForm::parse(body).is_none()
+                && !is_signed_in_page(url, body)
+                && maintenance::is_maintenance_page(body)
+            {
+                Err(Unavailable::default().into())
+            } else {
+                Ok(())
+            }
+        }
+        status => Err(LoginError::UnexpectedStatus(status).into()),
+    }
+}
+
+const LOCKED_WORDS: &[&str] = &["ロック", "locked"];
+const EXPIRED_WORDS: &[&str] = &["有効期限", "トークン", "token", "expired"];
+
+/// Tells why the sign-in form came back, from the message shown with it
+fn failure(body: &str) -> anyhow::Error {
+    let text = visible_text(body);
+    if mentions(&text, LOCKED_WORDS) {
+        LoginError::AccountLocked.into()
+    } else if maintenance::is_maintenance_page(body) {
+        Unavailable::default().into()
+    } else if mentions(&text, EXPIRED_WORDS) {
+        LoginError::FormExpired.into()
+    } else {
+        LoginError::InvalidCredentials.into()
+    }
+}
+
+pub fn mentions(text: &str, words: &[&str]) -> bool {
+    let text = text.to_lowercase();
+    words.iter().any(|word| text.contains(word))
+}
+
+/// The text of `html` without tags, scripts and styles, so that names of
+/// hidden fields like `_token` don't count as messages
+pub fn visible_text(html: &str) -> String {
+    static HIDDEN: OnceLock<Regex> = OnceLock::new();
+    static TAG: OnceLock<Regex> = OnceLock::new();
+    let hidden = HIDDEN.get_or_init(|| {
+        Regex::new(r"(?is)<script\b.*?</script>|<style\b.*?</style>|<!--.*?-->").unwrap()
+    });
+    let tag = TAG.get_or_init(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+    tag.replace_all(&hidden.replace_all(html, " "), " ")
+        .into_owned()
+}
+
+/// A form on a page with the attributes of its inputs
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Form {
+    action: Option<String>,
+    multipart: bool,
+    /// Hidden inputs, e.g. CSRF tokens, sent back as they are
+    hidden: Vec<(String, String)>,
+    inputs: Vec<Vec<(String, String)>>,
+    /// The markup between the form tags
+    contents: String,
+}
+
+impl Form {
+    fn all(html: &str) -> Vec<Self> {
+        static FORM: OnceLock<Regex> = OnceLock::new();
+        static INPUT: OnceLock<Regex> = OnceLock::new();
+        let form = FORM.get_or_init(|| Regex::new(r"(?is)<form\b([^>]*)>(.*?)</form>").unwrap());
+        let input = INPUT.get_or_init(|| Regex::new(r"(?is)<input\b([^>]*)>").unwrap());
+        form.captures_iter(html)
+            .map(|captures| {
+                let form_attrs = attributes(&captures[1]);
+                let inputs = input
+                    .captures_iter(&captures[2])
+                    .map(|input| attributes(&input[1]))
+                    .collect::<Vec<_>>();
+                let hidden = inputs
+                    .iter()
+                    .filter(|attrs| kind(attrs) == "hidden")
+                    .filter_map(|attrs| Some((attr(attrs, "name")?, attr(attrs, "value")?)))
+                    .collect();
+                Self {
+                    action: attr(&form_attrs, "action").filter(|action| !action.is_empty()),
+                    multipart: attr(&form_attrs, "enctype")
+                        .is_some_and(|enctype| enctype.eq_ignore_ascii_case("multipart/form-data")),
+                    hidden,
+                    inputs,
+                    contents: captures[2].to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Name of the first input of one of `kinds`
+    fn field(&self, kinds: &[&str]) -> Option<String> {
+        self.inputs
+            .iter()
+            .find(|attrs| kinds.contains(&kind(attrs).as_str()))
+            .and_then(|attrs| attr(attrs, "name"))
+    }
+}
+
+fn attr(attrs: &[(String, String)], name: &str) -> Option<String> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.clone())
+}
+
+/// The `type` of an input, `text` if omitted
+fn kind(attrs: &[(String, String)]) -> String {
+    attr(attrs, "type").map_or_else(|| "text".to_string(), |kind| kind.to_lowercase())
+}
+
+/// The form with a password input
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SigninForm {
+    form: Form,
+    email_field: String,
+    password_field: String,
+}
+
+impl SigninForm {
+    fn parse(html: &str) -> Option<Self> {
+        Form::all(html).into_iter().find_map(|form| {
+            let password_field = form.field(&["password"])?;
+            let email_field = form
+                .field(&["email", "text"])
+                .unwrap_or_else(|| "loginId".into());
+            Some(Self {
+                form,
+                email_field,
+                password_field,
+            })
+        })
+    }
+}
+
+/// Steps after the password are given up after this many
+const MAX_STEPS: u32 = 5;
+
+/// Path of the form of the code sent by email
+const VERIFY_PATH: &str = "/signin/verify";
+
+/// A form asking for a one-time code, e.g. one sent by email
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VerificationForm {
+    form: Form,
+    code_field: String,
+    /// What the form asks for
+    message: String,
+}
+
+impl VerificationForm {
+    fn parse(html: &str) -> Option<Self> {
+        Form::all(html).into_iter().find_map(|form| {
+            // Any field of the form known to ask for the code, otherwise the
+            // one named like a code
+            let known = form
+                .action
+                .as_deref()
+                .is_some_and(|action| action.trim_end_matches('/').ends_with(VERIFY_PATH));
+            let code_field = form
+                .inputs
+                .iter()
+                .filter(|attrs| matches!(kind(attrs).as_str(), "text" | "number" | "tel"))
+                .filter_map(|attrs| attr(attrs, "name"))
+                .find(|name| known || is_code_name(name))?;
+            let message = visible_text(&form.contents)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            Some(Self {
+                form,
+                code_field,
+                message,
+            })
+        })
+    }
+}
+
+/// Whether an input named `name` is for a one-time code, e.g. `otp` or
+/// `verification_code`, but not `postal_code`
+fn is_code_name(name: &str) -> bool {
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    let code = CODE.get_or_init(|| {
+        Regex::new(
+            r"^(?:auth|verify|verification|confirm|confirmation|onetime|otp|mfa|twofactor|2fa|security|login|signin|email|mail)?(?:code|otp|totp|pin|passcode|token)$",
+        )
+        .unwrap()
+    });
+    let name = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    code.is_match(&name)
+}
+
+/// Attributes of a tag with lowercase names and decoded values
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    let attribute = ATTRIBUTE.get_or_init(|| {
+        Regex::new(r#"([\w:-]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+    });
+    attribute
+        .captures_iter(tag)
+        .map(|captures| {
+            let value = captures
+                .get(2)
+                .or_else(|| captures.get(3))
+                .or_else(|| captures.get(4))
+                .map_or("", |value| value.as_str());
+            (captures[1].to_lowercase(), decode_entities(value))
+        })
+        .collect()
+}
+
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIGNIN_PAGE: &str = r#"
+        <form method="post" action="/signin" enctype="multipart/form-data">
+          <input type="hidden" name="_token" value="a&amp;b">
+          <input type=email name='mail' placeholder="メールアドレス">
+          <input type="password" name="pass">
+          <button type="submit">ログイン</button>
+        </form>"#;
+
+    #[test]
+    fn form_fields_and_tokens_are_found() {
+        let form = SigninForm::parse(SIGNIN_PAGE).unwrap();
+        assert_eq!(form.form.action.as_deref(), Some("/signin"));
+        assert!(form.form.multipart);
+        assert_eq!(form.email_field, "mail");
+        assert_eq!(form.password_field, "pass");
+        assert_eq!(form.form.hidden, vec![("_token".into(), "a&b".into())]);
+        assert_eq!(SigninForm::parse("<form><input name=q></form>"), None);
+        assert_eq!(VerificationForm::parse(SIGNIN_PAGE), None);
+    }
+
+    #[test]
+    fn verification_step_is_recognized() {
+        let page = r#"
+            <form method="post" action="/signin/verify">
+              <input type="hidden" name="_token" value="t">
+              <p>メールに記載された
+                 確認コードを入力してください</p>
+              <input type="text" name="authCode" inputmode="numeric">
+              <button type="submit">送信</button>
+            </form>"#;
+        let form = VerificationForm::parse(page).unwrap();
+        assert_eq!(form.code_field, "authCode");
+        assert_eq!(
+            form.message,
+            "メールに記載された 確認コードを入力してください 送信"
+        );
+        assert_eq!(form.form.action.as_deref(), Some("/signin/verify"));
+        assert_eq!(form.form.hidden, vec![("_token".into(), "t".into())]);
+        assert_eq!(SigninForm::parse(page), None);
+        assert_eq!(VerificationForm::parse("<form><input name=q></form>"), None);
+
+        let known = r#"<form action="/signin/verify"><input name="number"></form>"#;
+        assert_eq!(VerificationForm::parse(known).unwrap().code_field, "number");
+        for name in [
+            "otp",
+            "one_time_code",
+            "verification-code",
+            "pin",
+            "MFA_CODE",
+        ] {
+            assert!(is_code_name(name), "{name}");
+        }
+        for name in ["author", "shipping", "postal_code", "zip_code", "opinion"] {
+            assert!(!is_code_name(name), "{name}");
+            let page = format!(r#"<form action="/profile"><input name="{name}"></form>"#);
+            assert_eq!(VerificationForm::parse(&page), None);
+        }
+    }
+
+    fn login_error(err: anyhow::Error) -> LoginError {
+        err.downcast().unwrap()
+    }
+
+    #[test]
+    fn failures_are_told_apart() {
+        let page = |message: &str| format!("<p class=error>{message}</p>{SIGNIN_PAGE}");
+        assert_eq!(
+            login_error(failure(&page("IDまたはパスワードが違います"))),
+            LoginError::InvalidCredentials
+        );
+        assert_eq!(
+            login_error(failure(&page("アカウントがロックされました"))),
+            LoginError::AccountLocked
+        );
+        assert_eq!(
+            login_error(failure(&page("ページの有効期限が切れました"))),
+            LoginError::FormExpired
+        );
+        assert!(failure(&page("メンテナンス中のためログインできません")).is::<Unavailable>());
+        let url = Url::parse(BASE_URL).unwrap().join(SIGNIN_PATH).unwrap();
+        assert_eq!(
+            login_error(check_status(StatusCode::from_u16(419).unwrap(), &url, "").unwrap_err()),
+            LoginError::FormExpired
+        );
+        assert!(
+            check_status(StatusCode::OK, &url, "<h1>ただいまメンテナンス中です</h1>")
+                .unwrap_err()
+                .is::<Unavailable>()
+        );
+        assert!(check_status(StatusCode::OK, &url, SIGNIN_PAGE).is_ok());
+    }
+
+    #[test]
+    fn signed_in_page_announcing_maintenance_is_accepted() {
+        let home = Url::parse(BASE_URL).unwrap().join("/home").unwrap();
+        let page = r#"
+            <a href="/signout">ログアウト</a>
+            <h2>メンテナンスのお知らせ</h2>
+            <p>10月1日 2:00から4:00まではメンテナンス中のためご利用いただけません</p>"#;
+        assert!(is_signed_in_page(&home, page));
+        assert!(check_status(StatusCode::OK, &home, page).is_ok());
+        assert!(!is_signed_in_page(&home, "<p>連絡帳</p>"));
+
+        // Signing out by a script rather than a link
+        let page = r#"<button type="button" onclick="signOut()">ログアウト</button>"#;
+        assert!(is_signed_in_page(&home, page));
+        let page = r#"<a href="javascript:void(0)" id="logout-button">Log out</a>"#;
+        assert!(is_signed_in_page(&home, page));
+        let signin = Url::parse(BASE_URL).unwrap().join(SIGNIN_PATH).unwrap();
+        assert!(!is_signed_in_page(&signin, page));
+    }
+}
@@ -3,21 +3,39 @@
     windows_subsystem = "windows"
 )]
 
-use std::{
-    marker::Unpin,
-    ops::RangeInclusive,
-    path::{Path, PathBuf},
-    sync::Arc,
+mod archive;
+mod cli;
+mod client;
+mod crypto;
+mod desktop;
+mod diagnostics;
+mod export;
+mod logging;
+mod login;
+mod maintenance;
+mod report;
+mod scheduler;
+mod settings;
+mod sink;
+mod tray;
+mod validation;
+mod viewer;
+
+use std::path::{Path, PathBuf};
+
+use shared::{
+    CommandError, Credentials, ExportedMonth, InputError, Locale, MonthStatus, NetworkSettings,
+    OutputFormat, OverwritePolicy, Plan, Schedule, Settings, SinkConfig, Text, YearMonth,
 };
+use structopt::StructOpt;
+use tauri::{AppHandle, LogicalSize, Manager, State, Window};
 
-use anyhow::bail;
-use futures::stream::StreamExt;
-use reqwest::Client;
-use shared::{Credentials, Message, YearMonth};
-use tauri::{LogicalSize, Manager, Window};
-use tokio::{
-    fs::File,
-    io::{AsyncWrite, AsyncWriteExt, BufWriter},
+use crate::{
+    desktop::LastSaveLocation,
+    export::{ExportLock, ExportOptions, PendingLoginStep},
+    logging::LogDir,
+    scheduler::Scheduler,
+    settings::SettingsStore,
 };
 
 #[tauri::command]
@@ -25,112 +43,377 @@ fn default_save_location() -> Option<PathBuf> {
     dirs::download_dir().or_else(|| std::env::current_dir().ok())
 }
 
+// The arguments mirror the fields of the form
+#[allow(clippy::too_many_arguments)]
 #[tauri::command]
 async fn start(
     window: Window,
+    lock: State<'_, ExportLock>,
+    store: State<'_, SettingsStore>,
+    last_save_location: State<'_, LastSaveLocation>,
     creds: Credentials,
-    range: RangeInclusive<YearMonth>,
+    months: Vec<YearMonth>,
     save_location: PathBuf,
-) -> Result<(), String> {
-    start_body(&window, creds, range, save_location)
+    output_format: OutputFormat,
+    overwrite_policy: OverwritePolicy,
+    passphrase: Option<String>,
+    create_save_location: bool,
+) -> Result<(), CommandError> {
+    check_request(&creds, &months, &save_location, create_save_location)?;
+    let _guard = lock
+        .0
+        .try_lock()
+        .map_err(|_| CommandError::Failed(Text::ExportRunning.get(Locale::current()).into()))?;
+    tracing::info!("export started from the window");
+    let options = export_options(
+        &store,
+        save_location,
+        output_format,
+        overwrite_policy,
+        passphrase,
+    );
+    let report = export::start_body(&window, creds, months, options)
         .await
-        .map_err(|err| err.to_string())
+        .map_err(|err| {
+            tracing::error!(error = %format!("{err:#}"), "export failed");
+            CommandError::Failed(err.to_string())
+        })?;
+    // The webview may open files there only once the export saved some
+    let saved = report.archive.is_some()
+        || report
+            .months
+            .iter()
+            .any(|month| month.status != MonthStatus::Failed && month.path.is_some());
+    if saved {
+        *last_save_location.0.lock().unwrap() = Some(report.save_location);
+    }
+    Ok(())
 }
 
-async fn start_body(
-    window: &Window,
+/// Logs in and lists what `start` would do with the same arguments without
+/// writing anything.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+async fn plan(
+    window: Window,
+    store: State<'_, SettingsStore>,
     creds: Credentials,
-    range: RangeInclusive<YearMonth>,
+    months: Vec<YearMonth>,
     save_location: PathBuf,
-) -> anyhow::Result<()> {
-    let client = Arc::new(reqwest::Client::builder().cookie_store(true).build()?);
-    login(window, &client, creds).await?;
-    download_concurrently(window, client, &range, &save_location).await?;
-    Ok(())
+    output_format: OutputFormat,
+    overwrite_policy: OverwritePolicy,
+    passphrase: Option<String>,
+    create_save_location: bool,
+) -> Result<Plan, CommandError> {
+    check_request(&creds, &months, &save_location, create_save_location)?;
+    let options = export_options(
+        &store,
+        save_location,
+        output_format,
+        overwrite_policy,
+        passphrase,
+    );
+    export::plan(&window, creds, months, options)
+        .await
+        .map_err(|err| {
+            tracing::error!(error = %format!("{err:#}"), "dry run failed");
+            CommandError::Failed(err.to_string())
+        })
 }
 
-async fn login(window: &Window, client: &Client, creds: Credentials) -> anyhow::Result<()> {
-    window.emit_all("output", Message::message("ログイン中...".into()))?;
-    let form = reqwest::multipart::Form::new()
-        .text("loginId", creds.email.clone())
-        .text("loginPass", creds.password.clone());
-    let body = client
-        .post("https://app.ixsie.jp/signin")
-        .multipart(form)
-        .send()
-        .await?
-        .error_for_status()?
-        .text()
-        .await?;
-    if !body.contains("ログアウト") {
-        bail!("ログインに失敗しました。ログイン情報を確認してください。");
+fn export_options(
+    store: &SettingsStore,
+    save_location: PathBuf,
+    format: OutputFormat,
+    policy: OverwritePolicy,
+    passphrase: Option<String>,
+) -> ExportOptions {
+    let settings = store.get();
+    ExportOptions {
+        save_location,
+        format,
+        policy,
+        passphrase: passphrase.filter(|passphrase| !passphrase.is_empty()),
+        sinks: settings.sinks,
+        network: settings.network,
     }
-    window.emit_all("output", Message::message("ログイン成功".into()))?;
+}
+
+/// Answers the extra sign-in step the running export waits for, or cancels
+/// signing in with `None`
+#[tauri::command]
+async fn submit_login_step(
+    pending: State<'_, PendingLoginStep>,
+    answer: Option<String>,
+) -> Result<(), String> {
+    let sender = pending.0.lock().await.take();
+    let sender = sender.ok_or_else(|| Text::NoLoginStep.get(Locale::current()).to_string())?;
+    // The export may have ended in the meantime
+    let _ = sender.send(answer);
     Ok(())
 }
 
-async fn download_concurrently(
-    window: &Window,
-    client: Arc<Client>,
-    range: &RangeInclusive<YearMonth>,
+/// Rejects invalid requests of the webview before anything is done
+fn check_request(
+    creds: &Credentials,
+    months: &[YearMonth],
     save_location: &Path,
-) -> anyhow::Result<()> {
-    let months = tokio_stream::iter(YearMonth::iter_range(range));
-    let mut stream = months
-        .map(move |month| {
-            let url = generate_url(month);
-            let client = Arc::clone(&client);
-            async move {
-                let mut output = output_file(save_location, month).await?;
-                download(&client, &mut output, &url).await?;
-                output.flush().await?;
-                anyhow::Ok(month)
+    create_save_location: bool,
+) -> Result<(), InputError> {
+    validation::check_request(creds, months, save_location, create_save_location)
+        .inspect_err(|err| tracing::warn!(error = ?err, "invalid export request"))
+}
+
+/// Whether exported files can be saved into `save_location`
+#[tauri::command]
+fn check_save_location(save_location: PathBuf) -> Result<(), InputError> {
+    validation::check_save_location(&save_location)
+}
+
+#[tauri::command]
+fn get_settings(store: State<'_, SettingsStore>) -> Settings {
+    store.get().without_secrets()
+}
+
+/// Enables or disables scheduled exports. The credentials, the save location
+/// and the passphrase are stored only while the schedule is enabled.
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+fn save_schedule(
+    store: State<'_, SettingsStore>,
+    scheduler: State<'_, Scheduler>,
+    schedule: Schedule,
+    creds: Option<Credentials>,
+    save_location: Option<PathBuf>,
+    output_format: OutputFormat,
+    overwrite_policy: OverwritePolicy,
+    passphrase: Option<String>,
+) -> Result<(), String> {
+    let creds = creds.filter(|creds| !creds.email.is_empty() && !creds.password.is_empty());
+    if schedule.enabled && creds.is_none() && store.get().creds.is_none() {
+        return Err(Text::ScheduleNeedsCredentials.get(Locale::current()).into());
+    }
+    tracing::info!(
+        ?schedule,
+        ?output_format,
+        ?overwrite_policy,
+        "saving schedule"
+    );
+    store
+        .update(|settings| {
+            settings.schedule = schedule;
+            if schedule.enabled {
+                settings.creds = creds.or(settings.creds.take());
+                settings.save_location = save_location;
+                settings.output_format = output_format;
+                settings.overwrite_policy = overwrite_policy;
+                settings.passphrase = passphrase.filter(|passphrase| !passphrase.is_empty());
+            } else {
+                settings.creds = None;
+                settings.passphrase = None;
             }
         })
-        .buffer_unordered(4);
-    while let Some(res) = stream.next().await {
-        let message = res.map_or_else(Message::from, Message::from);
-        window.emit_all("output", message)?;
-    }
-    window.emit_all("output", Message::message("完了".into()))?;
+        .map_err(|err| err.to_string())?;
+    scheduler.reschedule();
     Ok(())
 }
 
-fn generate_url(month: YearMonth) -> String {
-    format!(
-        "https://app.ixsie.jp/user/contact/pdf?contactYear={}&contactMonth={}",
-        month.year,
-        month.month.number_from_month()
-    )
+/// Saves the network settings after checking that a client can be built with
+/// them. A proxy URL whose password was hidden by `get_settings` keeps the
+/// stored password.
+#[tauri::command]
+fn save_network_settings(
+    store: State<'_, SettingsStore>,
+    mut network: NetworkSettings,
+) -> Result<(), String> {
+    let stored = store.get().network;
+    if network.proxy.is_some() && network.proxy == stored.without_secrets().proxy {
+        network.proxy = stored.proxy;
+    }
+    client::build(&network).map_err(|err| format!("{err:#}"))?;
+    tracing::info!(network = ?network.without_secrets(), "saving network settings");
+    store
+        .update(|settings| settings.network = network)
+        .map_err(|err| err.to_string())
 }
 
-async fn output_file(save_location: &Path, month: YearMonth) -> anyhow::Result<BufWriter<File>> {
-    let file = tokio::fs::File::create(save_location.join(format!("{month}.pdf"))).await?;
-    Ok(BufWriter::new(file))
+#[tauri::command]
+fn add_sink(store: State<'_, SettingsStore>, sink: SinkConfig) -> Result<(), String> {
+    tracing::info!(sink = %sink.name(Locale::En), "adding backup target");
+    store
+        .update(|settings| settings.sinks.push(sink))
+        .map_err(|err| err.to_string())
 }
 
-async fn download(
-    client: &Client,
-    mut writer: impl AsyncWrite + Unpin,
-    url: &str,
-) -> anyhow::Result<()> {
-    let mut response = client.get(url).send().await?.error_for_status()?;
-    while let Some(chunk) = response.chunk().await? {
-        writer.write_all(&chunk).await?;
-    }
-    writer.flush().await?;
-    Ok(())
+#[tauri::command]
+fn remove_sink(store: State<'_, SettingsStore>, index: usize) -> Result<(), String> {
+    store
+        .update(|settings| {
+            if index < settings.sinks.len() {
+                settings.sinks.remove(index);
+            }
+        })
+        .map_err(|err| err.to_string())
+}
+
+/// Opens an exported file, which must be inside one of the save locations
+#[tauri::command]
+fn open_exported(
+    last_save_location: State<'_, LastSaveLocation>,
+    store: State<'_, SettingsStore>,
+    path: PathBuf,
+) -> Result<(), String> {
+    let roots = last_save_location.roots(&store);
+    desktop::open_inside(&roots, &path).map_err(|err| format!("{err:#}"))
+}
+
+/// Opens the folder the last export saved into in the file manager
+#[tauri::command]
+fn reveal_save_location(
+    last_save_location: State<'_, LastSaveLocation>,
+    store: State<'_, SettingsStore>,
+) -> Result<(), String> {
+    let roots = last_save_location.roots(&store);
+    desktop::open_root(&roots).map_err(|err| format!("{err:#}"))
+}
+
+/// The PDFs the viewer can show, from all save locations
+#[tauri::command]
+fn list_exported(
+    last_save_location: State<'_, LastSaveLocation>,
+    store: State<'_, SettingsStore>,
+) -> Vec<ExportedMonth> {
+    viewer::list_all(&last_save_location.roots(&store))
+}
+
+#[tauri::command]
+fn open_log_dir(logs: State<'_, LogDir>) -> Result<(), String> {
+    desktop::open(logs.path()).map_err(|err| format!("{err:#}"))
+}
+
+/// Writes the log files into a single file in `destination` and returns its
+/// path.
+#[tauri::command]
+fn export_log(logs: State<'_, LogDir>, destination: PathBuf) -> Result<PathBuf, String> {
+    logs.export(&destination)
+        .map_err(|err| Text::LogExportFailed.fill(Locale::current(), &[&format!("{err:#}")]))
+}
+
+/// Writes a zip for bug reports into `destination` and returns its path. The
+/// report of the last run is read from `save_location`, or from the one of
+/// scheduled exports.
+#[tauri::command]
+async fn export_diagnostics(
+    store: State<'_, SettingsStore>,
+    logs: State<'_, LogDir>,
+    destination: PathBuf,
+    save_location: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let settings = store.get();
+    let report = match save_location.or_else(|| settings.save_location.clone()) {
+        Some(dir) => report::read(&dir).await,
+        None => None,
+    };
+    diagnostics::bundle(&destination, &settings, report.as_ref(), &logs)
+        .await
+        .map_err(|err| {
+            Text::DiagnosticsExportFailed.fill(Locale::current(), &[&format!("{err:#}")])
+        })
+}
+
+#[tauri::command]
+fn get_locale() -> Locale {
+    Locale::current()
+}
+
+/// Switches the language of the messages and the tray menu, and remembers it
+/// instead of following the OS.
+#[tauri::command]
+fn set_locale(
+    app: AppHandle,
+    store: State<'_, SettingsStore>,
+    locale: Locale,
+) -> Result<(), String> {
+    tracing::info!(?locale, "switching language");
+    locale.set_current();
+    tray::update_menu(&app).map_err(|err| err.to_string())?;
+    store
+        .update(|settings| settings.locale = Some(locale))
+        .map_err(|err| err.to_string())
 }
 
 fn main() {
+    if let Some(tag) = sys_locale::get_locale() {
+        Locale::detect(&tag).set_current();
+    }
+    if let Some(command) = cli::Opt::from_args().command {
+        if let Err(err) = command.run() {
+            eprintln!(
+                "{}",
+                Text::ErrorLine.fill(Locale::current(), &[&format!("{err:#}")])
+            );
+            std::process::exit(1);
+        }
+        return;
+    }
     tauri::Builder::default()
+        .manage(ExportLock::default())
+        .manage(PendingLoginStep::default())
+        .manage(LastSaveLocation::default())
+        .manage(Scheduler::default())
+        .system_tray(tray::system_tray())
+        .on_system_tray_event(tray::on_system_tray_event)
+        .on_window_event(tray::on_window_event)
+        .register_uri_scheme_protocol(viewer::PROTOCOL, viewer::serve)
         .setup(|app| {
+            let config_dir = app
+                .path_resolver()
+                .app_config_dir()
+                .ok_or("failed to resolve the app config directory")?;
+            let data_dir = app
+                .path_resolver()
+                .app_data_dir()
+                .ok_or("failed to resolve the app data directory")?;
+            app.manage(logging::init(&data_dir.join("logs"))?);
+            let store = SettingsStore::load(&config_dir);
+            if let Some(locale) = store.get().locale {
+                locale.set_current();
+                tray::update_menu(&app.handle())?;
+            }
+            app.manage(store);
             if let Some(window) = app.get_window("main") {
                 window.set_min_size(Some(LogicalSize::new(300.0, 800.0)))?;
             }
+            let handle = app.handle();
+            tauri::async_runtime::spawn(async move {
+                let scheduler = handle.state::<Scheduler>();
+                let store = handle.state::<SettingsStore>();
+                scheduler
+                    .run(|| store.get().schedule, || tray::scheduled_export(&handle))
+                    .await;
+            });
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![default_save_location, start])
+        .invoke_handler(tauri::generate_handler![
+            default_save_location,
+            start,
+            plan,
+            submit_login_step,
+            check_save_location,
+            get_settings,
+            save_schedule,
+            save_network_settings,
+            add_sink,
+            remove_sink,
+            open_exported,
+            reveal_save_location,
+            list_exported,
+            open_log_dir,
+            export_log,
+            export_diagnostics,
+            get_locale,
+            set_locale
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,129 @@
+//! Scheduled maintenance and outages of ixsie, told apart from other failures
+//! so that the user gets a clear message and exports can wait for them to end.
+
+use std::{fmt, time::Duration};
+
+use chrono::{DateTime, Local, Utc};
+use reqwest::{header, Response, StatusCode};
+use shared::{Locale, Text};
+
+use crate::login;
+
+/// How long to wait when ixsie doesn't say when it is back
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
+
+/// Phrases of a page shown instead of ixsie while it is down, as opposed to
+/// notices of maintenance to come
+const MAINTENANCE_MARKERS: &[&str] = &[
+    "メンテナンス中",
+    "メンテナンスを実施しております",
+    "メンテナンスを行っております",
+    "under maintenance",
+    "down for maintenance",
+    "maintenance in progress",
+];
+
+/// ixsie is under maintenance or otherwise unavailable
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unavailable {
+    /// From the `Retry-After` header, if any
+    pub retry_after: Option<Duration>,
+}
+
+impl fmt::Display for Unavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let locale = Locale::current();
+        match self.retry_after.and_then(resume_time) {
+            Some(resume_at) => {
+                f.write_str(&Text::MaintenanceUntil.fill(locale, &[&resume_at.format("%H:%M")]))
+            }
+            None => f.write_str(Text::Maintenance.get(locale)),
+        }
+    }
+}
+
+impl std::error::Error for Unavailable {}
+
+/// When to try again after waiting `delay`, unless that is too far off to
+/// tell
+pub fn resume_time(delay: Duration) -> Option<DateTime<Local>> {
+    Local::now().checked_add_signed(chrono::Duration::from_std(delay).ok()?)
+}
+
+/// Fails with [`Unavailable`] if `response` is a 503
+pub fn check(response: &Response) -> Result<(), Unavailable> {
+    if response.status() != StatusCode::SERVICE_UNAVAILABLE {
+        return Ok(());
+    }
+    let retry_after = response
+        .headers()
+        .get(header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_retry_after(value, Utc::now()));
+    Err(Unavailable { retry_after })
+}
+
+/// Whether the page of `html` says that ixsie is under maintenance now
+pub fn is_maintenance_page(html: &str) -> bool {
+    login::mentions(&login::visible_text(html), MAINTENANCE_MARKERS)
+}
+
+/// A `Retry-After` value in seconds or as an HTTP date
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_is_read_as_seconds_or_date() {
+        let now = DateTime::parse_from_rfc3339("2023-05-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Mon, 01 May 2023 00:30:00 GMT", now),
+            Some(Duration::from_secs(30 * 60))
+        );
+        // Already past
+        assert_eq!(
+            parse_retry_after("Sun, 30 Apr 2023 23:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert!(is_maintenance_page(
+            "<h1>ただいまシステムメンテナンス中です</h1>"
+        ));
+        assert!(!is_maintenance_page("<p>連絡帳</p>"));
+        assert!(!is_maintenance_page(
+            "<h2>メンテナンスのお知らせ</h2><p>10月1日 2:00から4:00までメンテナンスを実施します</p>"
+        ));
+    }
+
+    #[test]
+    fn huge_retry_after_has_no_resume_time() {
+        let now = Utc::now();
+        let retry_after = parse_retry_after(&u64::MAX.to_string(), now);
+        assert_eq!(retry_after, Some(Duration::from_secs(u64::MAX)));
+        assert_eq!(resume_time(retry_after.unwrap()), None);
+        assert_eq!(
+            Unavailable { retry_after }.to_string(),
+            Unavailable::default().to_string()
+        );
+        assert!(resume_time(Duration::from_secs(60)).is_some());
+    }
+}
@@ -0,0 +1,154 @@
+use std::{fmt::Write as _, path::Path};
+
+use shared::{format_size, Locale, MonthReport, MonthStatus, RunReport, SinkReport, Text};
+
+pub const REPORT_JSON: &str = "ixsie-report.json";
+pub const REPORT_MARKDOWN: &str = "ixsie-report.md";
+
+/// Writes the machine-readable and the human-readable reports into `dir`.
+/// Each is replaced as a whole, since the next run reads the checksums from
+/// the JSON one.
+pub async fn write(report: &RunReport, dir: &Path) -> anyhow::Result<()> {
+    for (name, data) in render(report)? {
+        crate::export::write_file(dir.join(name), data, None).await?;
+    }
+    Ok(())
+}
+
+/// The report of the previous run in `dir`, if there is a readable one
+pub async fn read(dir: &Path) -> Option<RunReport> {
+    let json = tokio::fs::read(dir.join(REPORT_JSON)).await.ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// File names and contents of the machine-readable and the human-readable
+/// reports
+pub fn render(report: &RunReport) -> anyhow::Result<[(&'static str, Vec<u8>); 2]> {
+    Ok([
+        (REPORT_JSON, serde_json::to_vec_pretty(report)?),
+        (REPORT_MARKDOWN, to_markdown(report).into_bytes()),
+    ])
+}
+
+fn to_markdown(report: &RunReport) -> String {
+    let locale = Locale::current();
+    let text = |text: Text| text.get(locale);
+    let mut md = String::new();
+    // Writing into a String never fails
+    let _ = writeln!(md, "# {}\n", text(Text::ReportTitle));
+    let _ = writeln!(
+        md,
+        "- {}: {} 〜 {}",
+        text(Text::RunAt),
+        report.started_at.format("%Y-%m-%d %H:%M:%S"),
+        report.finished_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    let _ = writeln!(
+        md,
+        "- {}: {} 〜 {}",
+        text(Text::Period),
+        report.range.start,
+        report.range.end
+    );
+    let _ = writeln!(
+        md,
+        "- {}: {}",
+        text(Text::SaveLocation),
+        report.save_location.display()
+    );
+    if let Some(archive) = &report.archive {
+        let _ = writeln!(md, "- {}: {}", text(Text::Archive), archive.display());
+    }
+    if !report.sinks.is_empty() {
+        let _ = writeln!(
+            md,
+            "- {}: {}",
+            text(Text::Backup),
+            sinks_summary(&report.sinks, locale)
+        );
+    }
+    let changed = report
+        .changed_months()
+        .map(|month| month.to_string())
+        .collect::<Vec<_>>();
+    if !changed.is_empty() {
+        let _ = writeln!(
+            md,
+            "- {}: {}",
+            text(Text::ChangedMonths),
+            changed.join(", ")
+        );
+    }
+    let _ = writeln!(
+        md,
+        "- {}\n",
+        Text::Counts.fill(
+            locale,
+            &[
+                &report.count(MonthStatus::Done),
+                &report.count(MonthStatus::Failed),
+                &format_size(report.total_size()),
+            ]
+        )
+    );
+    let columns = [
+        text(Text::Month),
+        text(Text::Status),
+        text(Text::Action),
+        text(Text::Size),
+        "SHA-256",
+        text(Text::Duration),
+        text(Text::Backup),
+        text(Text::Error),
+    ];
+    let _ = writeln!(md, "| {} |", columns.join(" | "));
+    let _ = writeln!(md, "|{}|", ["---"; 8].join("|"));
+    for month in &report.months {
+        let _ = writeln!(
+            md,
+            "| {} | {} | {} | {} | {} | {} | {} | {} |",
+            month.month,
+            month.status.label(locale),
+            action_summary(month, locale),
+            month.size.map(format_size).unwrap_or_default(),
+            month.sha256.as_deref().unwrap_or_default(),
+            Text::Seconds.fill(
+                locale,
+                &[&format!("{:.1}", month.duration_ms as f64 / 1000.0)]
+            ),
+            escape(&sinks_summary(&month.sinks, locale)),
+            escape(month.error.as_deref().unwrap_or_default()),
+        );
+    }
+    md
+}
+
+/// e.g. "上書き (変更あり: versions/2023-04_20240315-093000.pdf)"
+fn action_summary(month: &MonthReport, locale: Locale) -> String {
+    let action = month
+        .action
+        .map(|action| action.label(locale))
+        .unwrap_or_default();
+    let changed = Text::Changed.get(locale);
+    match (month.changed, &month.previous_version) {
+        (_, Some(version)) => format!("{action} ({changed}: {})", version.display()),
+        (true, None) => format!("{action} ({changed})"),
+        (false, None) => action.to_string(),
+    }
+}
+
+/// e.g. "WebDAV https://example.com: 成功, S3 …: 失敗 (…)"
+fn sinks_summary(sinks: &[SinkReport], locale: Locale) -> String {
+    sinks
+        .iter()
+        .map(|sink| match &sink.error {
+            Some(err) => Text::SinkFailed.fill(locale, &[&sink.name, err]),
+            None => Text::SinkSucceeded.fill(locale, &[&sink.name]),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn escape(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', " ")
+}
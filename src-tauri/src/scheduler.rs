@@ -0,0 +1,142 @@
+//! Timing of scheduled exports. Independent of the tray and of tauri so that
+//! it can be tested on its own.
+
+use std::future::Future;
+
+use chrono::{DateTime, Duration, Local, NaiveTime, TimeZone};
+use shared::{OverwritePolicy, Schedule, YearMonth};
+use tokio::sync::Notify;
+
+/// The first time strictly after `now` at which `schedule` fires, or `None`
+/// if the schedule is disabled or its time is invalid.
+pub fn next_run<Tz: TimeZone>(schedule: &Schedule, now: &DateTime<Tz>) -> Option<DateTime<Tz>> {
+    if !schedule.enabled {
+        return None;
+    }
+    let time = NaiveTime::from_hms_opt(schedule.hour, schedule.minute, 0)?;
+    let today = now.date_naive();
+    // Look one day further in case the time falls into a DST gap
+    (0..=2).find_map(|days| {
+        let naive = (today + Duration::days(days)).and_time(time);
+        now.timezone()
+            .from_local_datetime(&naive)
+            .earliest()
+            .filter(|run| run > now)
+    })
+}
+
+/// Scheduled exports only fetch the most recent months, which are the ones
+/// teachers are still writing in.
+pub fn scheduled_months(today: YearMonth) -> Vec<YearMonth> {
+    vec![today.pred(), today]
+}
+
+/// Scheduled exports are incremental: unless the saved policy skips existing
+/// files, months are only rewritten when their contents changed, with the
+/// previous file kept.
+pub fn scheduled_policy(saved: OverwritePolicy) -> OverwritePolicy {
+    match saved {
+        OverwritePolicy::Skip => OverwritePolicy::Skip,
+        _ => OverwritePolicy::IfChanged,
+    }
+}
+
+/// Wakes up the scheduler loop when the schedule changes
+#[derive(Default)]
+pub struct Scheduler {
+    wake: Notify,
+}
+
+impl Scheduler {
+    pub fn reschedule(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Runs `job` every time the schedule returned by `schedule` fires. Never returns.
+    pub async fn run<S, J, F>(&self, schedule: S, mut job: J)
+    where
+        S: Fn() -> Schedule,
+        J: FnMut() -> F,
+        F: Future<Output = ()>,
+    {
+        loop {
+            let Some(next) = next_run(&schedule(), &Local::now()) else {
+                self.wake.notified().await;
+                continue;
+            };
+            let delay = (next - Local::now()).to_std().unwrap_or_default();
+            tracing::debug!(%next, "next scheduled export");
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => job().await,
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Timelike, Utc};
+    use shared::Month;
+
+    use super::*;
+
+    fn schedule(hour: u32, minute: u32) -> Schedule {
+        Schedule {
+            enabled: true,
+            hour,
+            minute,
+        }
+    }
+
+    #[test]
+    fn next_run_later_today() {
+        let now = Utc.with_ymd_and_hms(2023, 4, 1, 12, 0, 0).unwrap();
+        let next = next_run(&schedule(21, 0), &now).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2023, 4, 1, 21, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn next_run_tomorrow() {
+        let now = Utc.with_ymd_and_hms(2023, 4, 30, 21, 0, 0).unwrap();
+        let next = next_run(&schedule(21, 0), &now).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2023, 5, 1, 21, 0, 0).unwrap());
+        assert_eq!(next.hour(), 21);
+    }
+
+    #[test]
+    fn next_run_disabled_or_invalid() {
+        let now = Utc.with_ymd_and_hms(2023, 4, 1, 12, 0, 0).unwrap();
+        assert_eq!(next_run(&Schedule::default(), &now), None);
+        assert_eq!(next_run(&schedule(25, 0), &now), None);
+    }
+
+    #[test]
+    fn scheduled_months_span_new_year() {
+        let jan = YearMonth {
+            year: 2024,
+            month: Month::January,
+        };
+        let dec = YearMonth {
+            year: 2023,
+            month: Month::December,
+        };
+        assert_eq!(scheduled_months(jan), vec![dec, jan]);
+    }
+
+    #[test]
+    fn scheduled_exports_are_incremental() {
+        assert_eq!(
+            scheduled_policy(OverwritePolicy::default()),
+            OverwritePolicy::IfChanged
+        );
+        assert_eq!(
+            scheduled_policy(OverwritePolicy::KeepBoth),
+            OverwritePolicy::IfChanged
+        );
+        assert_eq!(
+            scheduled_policy(OverwritePolicy::Skip),
+            OverwritePolicy::Skip
+        );
+    }
+}
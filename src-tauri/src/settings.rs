@@ -0,0 +1,123 @@
+use std::{
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::bail;
+use chrono::Local;
+use shared::{Locale, Settings, Text};
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Settings backed by a JSON file in the app config directory
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Mutex<Settings>,
+    /// False if the file couldn't be read and is still there, so that the
+    /// stored settings aren't replaced by the defaults
+    writable: bool,
+}
+
+impl SettingsStore {
+    /// Loads the settings from `dir`, falling back to the defaults if the file
+    /// is missing or unreadable. A broken file is renamed so that it isn't
+    /// overwritten.
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(SETTINGS_FILE);
+        let (settings, writable) = match std::fs::read(&path) {
+            Ok(json) => match serde_json::from_slice(&json) {
+                Ok(settings) => (settings, true),
+                Err(err) => {
+                    tracing::error!(error = %err, path = %path.display(), "broken settings");
+                    (Settings::default(), keep_broken(&path))
+                }
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => (Settings::default(), true),
+            Err(err) => {
+                tracing::error!(error = %err, path = %path.display(), "cannot read the settings");
+                (Settings::default(), false)
+            }
+        };
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            writable,
+        }
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.lock().unwrap().clone()
+    }
+
+    /// Applies `f` to the settings and writes them back to disk.
+    pub fn update(&self, f: impl FnOnce(&mut Settings)) -> anyhow::Result<()> {
+        if !self.writable {
+            bail!(Text::SettingsKept.fill(Locale::current(), &[&self.path.display()]));
+        }
+        let mut settings = self.settings.lock().unwrap();
+        f(&mut settings);
+        let dir = self.path.parent().unwrap_or(Path::new("."));
+        std::fs::create_dir_all(dir)?;
+        let mut builder = tempfile::Builder::new();
+        // The file contains the login password and other secrets, so it must
+        // never be readable by others, not even before it is complete
+        #[cfg(unix)]
+        builder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600));
+        let mut file = builder.tempfile_in(dir)?;
+        file.write_all(&serde_json::to_vec_pretty(&*settings)?)?;
+        file.as_file().sync_all()?;
+        file.persist(&self.path)?;
+        Ok(())
+    }
+}
+
+/// Renames a broken settings file to keep it for the user, returning whether
+/// it is out of the way
+fn keep_broken(path: &Path) -> bool {
+    let backup = path.with_extension(format!("{}.broken", Local::now().format("%Y%m%d%H%M%S")));
+    match std::fs::rename(path, &backup) {
+        Ok(()) => {
+            tracing::warn!(path = %backup.display(), "kept the broken settings");
+            true
+        }
+        Err(err) => {
+            tracing::error!(error = %err, "cannot keep the broken settings");
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_settings_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(SETTINGS_FILE), b"{ broken").unwrap();
+        let store = SettingsStore::load(dir.path());
+        assert_eq!(store.get(), Settings::default());
+        store
+            .update(|settings| settings.locale = Some(Locale::En))
+            .unwrap();
+
+        let names = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names.len(), 2);
+        let backup = names.iter().find(|name| name.ends_with(".broken")).unwrap();
+        assert_eq!(std::fs::read(dir.path().join(backup)).unwrap(), b"{ broken");
+        assert_eq!(
+            SettingsStore::load(dir.path()).get().locale,
+            Some(Locale::En)
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(dir.path().join(SETTINGS_FILE)).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::bail;
+use shared::{Locale, Message, MonthStatus, Text, YearMonth};
+use tauri::{
+    api::notification::Notification, AppHandle, CustomMenuItem, GlobalWindowEvent, Manager,
+    SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem, WindowEvent,
+};
+
+use crate::{
+    export::{self, Emitter, ExportLock, ExportOptions},
+    scheduler::{scheduled_months, scheduled_policy},
+    settings::SettingsStore,
+};
+
+const SHOW: &str = "show";
+const EXPORT_NOW: &str = "export-now";
+const QUIT: &str = "quit";
+
+pub fn system_tray() -> SystemTray {
+    SystemTray::new().with_menu(menu(Locale::current()))
+}
+
+fn menu(locale: Locale) -> SystemTrayMenu {
+    SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new(SHOW, Text::TrayShow.get(locale)))
+        .add_item(CustomMenuItem::new(
+            EXPORT_NOW,
+            Text::TrayRunNow.get(locale),
+        ))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(QUIT, Text::TrayQuit.get(locale)))
+}
+
+/// Rebuilds the menu in the current locale.
+pub fn update_menu(app: &AppHandle) -> tauri::Result<()> {
+    app.tray_handle().set_menu(menu(Locale::current()))
+}
+
+pub fn on_system_tray_event(app: &AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_main_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+            SHOW => show_main_window(app),
+            EXPORT_NOW => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move { scheduled_export(&app).await });
+            }
+            QUIT => app.exit(0),
+            _ => {}
+        },
+        _ => {}
+    }
+}
+
+/// Keeps the app running in the tray when the window is closed while
+/// scheduled exports are enabled.
+pub fn on_window_event(event: GlobalWindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event.event() {
+        if event
+            .window()
+            .state::<SettingsStore>()
+            .get()
+            .schedule
+            .enabled
+        {
+            let _ = event.window().hide();
+            api.prevent_close();
+        }
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_window("main") {
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Exports the recent months with the stored settings and notifies the user
+/// if anything went wrong.
+pub async fn scheduled_export(app: &AppHandle) {
+    let settings = app.state::<SettingsStore>().get();
+    let result = async {
+        let (Some(creds), Some(save_location)) = (settings.creds, settings.save_location) else {
+            bail!(Text::ScheduleNotConfigured.get(Locale::current()));
+        };
+        let lock = app.state::<ExportLock>();
+        let _guard = lock.0.lock().await;
+        let months = scheduled_months(YearMonth::current());
+        tracing::info!("scheduled export started");
+        let options = ExportOptions {
+            save_location,
+            format: settings.output_format,
+            policy: scheduled_policy(settings.overwrite_policy),
+            passphrase: settings.passphrase,
+            sinks: settings.sinks,
+            network: settings.network,
+        };
+        export::start_body(app, creds, months, options).await
+    }
+    .await;
+    let failure = match result {
+        Ok(report) if report.count(MonthStatus::Failed) == 0 => return,
+        Ok(report) => Text::ScheduledMonthsFailed
+            .fill(Locale::current(), &[&report.count(MonthStatus::Failed)]),
+        Err(err) => {
+            tracing::error!(error = %format!("{err:#}"), "scheduled export failed");
+            let _ = Emitter::emit(app, Message::error(err.to_string()));
+            err.to_string()
+        }
+    };
+    let _ = Notification::new(&app.config().tauri.bundle.identifier)
+        .title(Text::ScheduledExportFailed.get(Locale::current()))
+        .body(failure)
+        .show();
+}
@@ -0,0 +1,83 @@
+//! Checks of the requests to export, on top of those the form already does,
+//! since the backend shouldn't trust what the webview sends
+
+use std::path::Path;
+
+use shared::{check_email, check_months, check_password, Credentials, InputError, YearMonth};
+
+/// Checks a request to export `months` into `save_location`, creating the
+/// directory first if `create` is set and it doesn't exist
+pub fn check_request(
+    creds: &Credentials,
+    months: &[YearMonth],
+    save_location: &Path,
+    create: bool,
+) -> Result<(), InputError> {
+    check_email(&creds.email)?;
+    check_password(&creds.password)?;
+    check_months(months, YearMonth::current())?;
+    if create && !save_location.as_os_str().is_empty() && !save_location.exists() {
+        tracing::info!(path = %save_location.display(), "creating the save location");
+        std::fs::create_dir_all(save_location).map_err(|_| InputError::SaveLocationNotWritable)?;
+    }
+    check_save_location(save_location)
+}
+
+/// The save location must be an existing directory files can be created in
+pub fn check_save_location(path: &Path) -> Result<(), InputError> {
+    if path.as_os_str().is_empty() {
+        return Err(InputError::SaveLocationMissing);
+    }
+    if !path.is_dir() {
+        return Err(InputError::SaveLocationNotFound);
+    }
+    // Permissions alone don't tell, e.g. with ACLs or read-only mounts
+    tempfile::tempfile_in(path).map_err(|_| InputError::SaveLocationNotWritable)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_location_must_be_an_existing_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(check_save_location(dir.path()), Ok(()));
+        assert_eq!(
+            check_save_location(&dir.path().join("missing")),
+            Err(InputError::SaveLocationNotFound)
+        );
+        let file = dir.path().join("file.pdf");
+        std::fs::write(&file, b"").unwrap();
+        assert_eq!(
+            check_save_location(&file),
+            Err(InputError::SaveLocationNotFound)
+        );
+        assert_eq!(
+            check_save_location(Path::new("")),
+            Err(InputError::SaveLocationMissing)
+        );
+    }
+
+    #[test]
+    fn missing_save_location_is_created_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let creds = Credentials {
+            email: "parent@example.com".into(),
+            password: "secret".into(),
+        };
+        let months = [YearMonth::current()];
+        let nested = dir.path().join("ixsie").join("2023");
+        assert_eq!(
+            check_request(&creds, &months, &nested, false),
+            Err(InputError::SaveLocationNotFound)
+        );
+        assert_eq!(check_request(&creds, &months, &nested, true), Ok(()));
+        assert!(nested.is_dir());
+        assert_eq!(
+            check_request(&creds, &[], &nested, false),
+            Err(InputError::NoMonths)
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! Lists the exported PDFs and serves them to the viewer of the webview
+//! through the `exports` protocol, which only reads inside the save locations
+
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::bail;
+use percent_encoding::percent_decode_str;
+use shared::{ExportedMonth, YearMonth};
+use tauri::{
+    http::{Request, Response, ResponseBuilder},
+    AppHandle, Manager,
+};
+
+use crate::{desktop::LastSaveLocation, settings::SettingsStore};
+
+pub const PROTOCOL: &str = "exports";
+
+/// PDFs of months in `dir`. Encrypted files and archives can't be shown and
+/// are left out.
+fn list(dir: &Path) -> anyhow::Result<Vec<ExportedMonth>> {
+    let mut months = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let Some(month) = path
+            .file_name()
+            .and_then(|name| name.to_str()?.strip_suffix(".pdf"))
+            .and_then(|stem| YearMonth::from_str(stem).ok())
+        else {
+            continue;
+        };
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            months.push(ExportedMonth {
+                month,
+                path,
+                size: metadata.len(),
+            });
+        }
+    }
+    Ok(months)
+}
+
+/// The months of all `roots` that still exist, newest first and without
+/// duplicates if a folder is among them twice
+pub fn list_all(roots: &[PathBuf]) -> Vec<ExportedMonth> {
+    let mut months = roots
+        .iter()
+        .filter(|root| root.is_dir())
+        .flat_map(|root| {
+            list(root).unwrap_or_else(|err| {
+                tracing::warn!(path = %root.display(), error = %format!("{err:#}"), "cannot list");
+                Vec::new()
+            })
+        })
+        .collect::<Vec<_>>();
+    months.sort_by(|a, b| b.month.cmp(&a.month).then_with(|| a.path.cmp(&b.path)));
+    months.dedup_by(|a, b| a.path == b.path);
+    months
+}
+
+/// Handler of the protocol. Anything but a PDF inside the save locations is
+/// answered with 404.
+pub fn serve(app: &AppHandle, request: &Request) -> Result<Response, Box<dyn std::error::Error>> {
+    let roots = app
+        .state::<LastSaveLocation>()
+        .roots(&app.state::<SettingsStore>());
+    let pdf = resolve(&roots, request.uri()).and_then(|path| Ok(std::fs::read(path)?));
+    match pdf {
+        Ok(pdf) => ResponseBuilder::new()
+            .status(200)
+            .mimetype("application/pdf")
+            .body(pdf),
+        Err(err) => {
+            tracing::warn!(uri = request.uri(), error = %format!("{err:#}"), "not serving");
+            ResponseBuilder::new().status(404).body(Vec::new())
+        }
+    }
+}
+
+/// The file to serve for `uri`, if it is a PDF inside one of `roots`
+fn resolve(roots: &[PathBuf], uri: &str) -> anyhow::Result<PathBuf> {
+    let path = crate::desktop::inside(roots, &requested_path(uri)?)?;
+    if path.extension().is_none_or(|extension| extension != "pdf") {
+        bail!("not a PDF: {}", path.display());
+    }
+    Ok(path)
+}
+
+/// The path encoded in a URL made by `convertFileSrc`, which is
+/// `exports://localhost/<path>` or `https://exports.localhost/<path>` on
+/// Windows
+fn requested_path(uri: &str) -> anyhow::Result<PathBuf> {
+    let url = reqwest::Url::parse(uri)?;
+    let path = percent_decode_str(url.path().trim_start_matches('/')).decode_utf8()?;
+    Ok(PathBuf::from(path.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_pdfs_of_months_are_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "2023-04.pdf",
+            "2023-05.pdf",
+            "2023-06.pdf.enc",
+            "ixsie-report.json",
+            "notes.pdf",
+        ] {
+            std::fs::write(dir.path().join(name), b"%PDF").unwrap();
+        }
+        let root = dir.path().to_path_buf();
+        let months = list_all(&[root.clone(), root]);
+        let names = months
+            .iter()
+            .map(|month| month.path.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["2023-05.pdf", "2023-04.pdf"]);
+        assert_eq!(months[0].size, 4);
+    }
+
+    #[test]
+    fn only_pdfs_inside_the_roots_are_served() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("exports");
+        std::fs::create_dir(&root).unwrap();
+        let pdf = root.join("2023-04.pdf");
+        std::fs::write(&pdf, b"%PDF").unwrap();
+        std::fs::write(root.join("ixsie-report.json"), b"{}").unwrap();
+        std::fs::write(dir.path().join("secret.pdf"), b"%PDF").unwrap();
+        let roots = [root.clone()];
+        let url = |path: &Path| {
+            let encoded = percent_encoding::utf8_percent_encode(
+                path.to_str().unwrap(),
+                percent_encoding::NON_ALPHANUMERIC,
+            );
+            format!("{PROTOCOL}://localhost/{encoded}")
+        };
+
+        assert_eq!(
+            resolve(&roots, &url(&pdf)).unwrap(),
+            pdf.canonicalize().unwrap()
+        );
+        assert!(resolve(&roots, &url(&root.join("ixsie-report.json"))).is_err());
+        assert!(resolve(&roots, &url(&root.join("../secret.pdf"))).is_err());
+        assert!(resolve(&roots, &url(&dir.path().join("secret.pdf"))).is_err());
+    }
+}
@@ -1,31 +1,41 @@
 use std::{
     borrow::Cow,
     fmt::{Debug, Display},
-    ops::{Deref, RangeInclusive},
+    ops::Deref,
     path::{Path, PathBuf},
     rc::Rc,
     str::FromStr,
 };
 
-use chrono::{Datelike, Local};
 use futures::StreamExt;
 use gloo_utils::format::JsValueSerdeExt;
-use num_traits::cast::FromPrimitive;
 use serde::{Deserialize, Serialize};
-use serde_wasm_bindgen::to_value;
-use shared::{Credentials, Message, Month, YearMonth};
+use serde_wasm_bindgen::{from_value, to_value};
+use shared::{
+    check_email, check_passphrase, check_password, check_range, CommandError, Credentials,
+    EraYearMonth, InputError, Locale, Message, OutputFormat, OverwritePolicy, Plan, RangeSpec,
+    RunReport, Text, YearMonth, YearMonthRange, MAX_MONTHS,
+};
 use tauri_sys::event;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use yew::prelude::*;
 
+use crate::{
+    backup::BackupForm, log::LogForm, login_step::LoginStepForm, months::MonthTable,
+    network::NetworkForm, plan::PlanView, schedule::ScheduleForm, summary::Summary, viewer::Viewer,
+};
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"], catch)]
-    async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+    pub(crate) async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
 
     #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "dialog"])]
-    async fn open(options: JsValue) -> JsValue;
+    pub(crate) async fn open(options: JsValue) -> JsValue;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "tauri"], js_name = convertFileSrc)]
+    pub(crate) fn convert_file_src(path: &str, protocol: &str) -> String;
 
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str);
@@ -35,8 +45,21 @@ extern "C" {
 #[serde(rename_all = "camelCase")]
 struct StartArgs {
     creds: Credentials,
-    range: RangeInclusive<YearMonth>,
+    months: Vec<YearMonth>,
     save_location: PathBuf,
+    output_format: OutputFormat,
+    overwrite_policy: OverwritePolicy,
+    passphrase: Option<String>,
+    create_save_location: bool,
+}
+
+/// Whether `run` downloads or only lists what would be downloaded
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RunMode {
+    Download,
+    DryRun,
+    /// Downloads some months of the last run again, keeping the others
+    Retry,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -46,11 +69,10 @@ struct Progress {
 }
 
 impl Progress {
-    fn new(range: &RangeInclusive<YearMonth>) -> Self {
-        let total = YearMonth::iter_range(range).count();
+    fn new(range: &YearMonthRange) -> Self {
         Self {
             processed: 0,
-            total,
+            total: range.len(),
         }
     }
 
@@ -106,6 +128,35 @@ impl Output {
     fn iter(&self) -> impl Iterator<Item = &Message> + '_ {
         self.0.iter()
     }
+
+    /// Months that failed to download in the current run
+    fn failed_months(&self) -> Vec<YearMonth> {
+        let mut months = self
+            .iter()
+            .filter_map(|message| match message {
+                Message::Failed(month, _) => Some(*month),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        months.sort();
+        months
+    }
+
+    /// Messages that aren't about a month, which the status table doesn't
+    /// show
+    fn general(&self) -> impl Iterator<Item = &Message> + '_ {
+        self.0.iter().filter(|message| message.month().is_none())
+    }
+
+    /// The maintenance notice to show, unless a month has been downloaded
+    /// since
+    fn maintenance(&self) -> Option<&Message> {
+        self.0
+            .iter()
+            .rev()
+            .find(|message| matches!(message, Message::Unavailable(_) | Message::Complete(_)))
+            .filter(|message| matches!(message, Message::Unavailable(_)))
+    }
 }
 
 impl Display for Output {
@@ -117,6 +168,8 @@ impl Display for Output {
 enum OutputAction {
     Message(Message),
     Clear,
+    /// Drops the messages about the months downloaded again
+    Forget(Vec<YearMonth>),
 }
 
 impl Reducible for Output {
@@ -131,33 +184,191 @@ impl Reducible for Output {
             OutputAction::Clear => {
                 output.clear();
             }
+            OutputAction::Forget(months) => {
+                output
+                    .retain(|message| message.month().is_none_or(|month| !months.contains(&month)));
+            }
         }
         Self(output).into()
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckSaveLocationArgs<'a> {
+    save_location: &'a Path,
+}
+
+/// Asks the backend whether files can be saved into `path`
+async fn check_save_location(path: Option<&Path>) -> Result<(), InputError> {
+    let path = path.ok_or(InputError::SaveLocationMissing)?;
+    let args = to_value(&CheckSaveLocationArgs {
+        save_location: path,
+    })
+    .unwrap();
+    invoke("check_save_location", args)
+        .await
+        .map(|_| ())
+        .map_err(|err| from_value(err).unwrap_or(InputError::SaveLocationNotWritable))
+}
+
+/// Problems with the fields of the form, shown below them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct FormErrors {
+    email: Option<InputError>,
+    password: Option<InputError>,
+    range: Option<InputError>,
+    save_location: Option<InputError>,
+    passphrase: Option<InputError>,
+}
+
+impl FormErrors {
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Shows an error the backend found next to the field it is about
+    fn from_backend(err: InputError) -> Self {
+        match err {
+            InputError::EmailMissing | InputError::EmailInvalid => Self {
+                email: Some(err),
+                ..Self::default()
+            },
+            InputError::PasswordMissing => Self {
+                password: Some(err),
+                ..Self::default()
+            },
+            InputError::PassphraseMismatch => Self {
+                passphrase: Some(err),
+                ..Self::default()
+            },
+            InputError::SaveLocationMissing
+            | InputError::SaveLocationNotFound
+            | InputError::SaveLocationNotWritable => Self {
+                save_location: Some(err),
+                ..Self::default()
+            },
+            _ => Self {
+                range: Some(err),
+                ..Self::default()
+            },
+        }
+    }
+}
+
+/// Reports the error of the `start` or `plan` command in the form or the
+/// output view
+fn report_command_error(
+    err: JsValue,
+    form_errors: &UseStateHandle<FormErrors>,
+    output: &UseReducerHandle<Output>,
+) {
+    match from_value::<CommandError>(err.clone()) {
+        Ok(CommandError::Invalid(err)) => form_errors.set(FormErrors::from_backend(err)),
+        Ok(CommandError::Failed(message)) => {
+            output.dispatch(OutputAction::Message(Message::error(message)))
+        }
+        // E.g. arguments the command couldn't deserialize
+        Err(_) => output.dispatch(OutputAction::Message(Message::error(
+            err.as_string().unwrap_or_default(),
+        ))),
+    }
+}
+
+#[derive(Serialize)]
+struct SetLocaleArgs {
+    locale: Locale,
+}
+
+/// Unwraps an `Option` or reports the invalid input `$name` to the output view and returns.
+macro_rules! or_report {
+    ( $output:expr, $locale:expr, $name:expr, $x:expr $(,)? ) => {
+        match $x {
+            Some(v) => v,
+            None => {
+                $output.dispatch(OutputAction::Message(Message::error(
+                    Text::Invalid.fill($locale, &[&$name.get($locale)]),
+                )));
+                return;
+            }
+        }
+    };
+}
+
 #[function_component(App)]
 pub fn app() -> HtmlResult {
+    let locale = use_state_eq(|| {
+        Locale::detect(
+            &gloo_utils::window()
+                .navigator()
+                .language()
+                .unwrap_or_default(),
+        )
+    });
+    {
+        let locale = locale.clone();
+        use_effect_with_deps(
+            |_| {
+                spawn_local(async move {
+                    let current = invoke("get_locale", to_value(&()).unwrap())
+                        .await
+                        .ok()
+                        .and_then(|val| from_value::<Locale>(val).ok());
+                    if let Some(current) = current {
+                        locale.set(current);
+                    }
+                });
+            },
+            (),
+        );
+    }
+    let change_locale = {
+        let locale = locale.clone();
+        Callback::from(move |event: Event| {
+            let index = event
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .selected_index();
+            if let Some(&next) = usize::try_from(index)
+                .ok()
+                .and_then(|index| Locale::ALL.get(index))
+            {
+                locale.set(next);
+                spawn_local(async move {
+                    let args = to_value(&SetLocaleArgs { locale: next }).unwrap();
+                    if let Err(err) = invoke("set_locale", args).await {
+                        log(&format!("{err:?}"));
+                    }
+                });
+            }
+        })
+    };
+    let text = |key: Text| key.get(*locale);
+
     let login_email_ref = use_node_ref();
     let login_password_ref = use_node_ref();
+    let passphrase_ref = use_node_ref();
+    let passphrase_confirm_ref = use_node_ref();
     let range_from_ref = use_node_ref();
     let range_to_ref = use_node_ref();
 
-    let range_from = use_state_eq(|| YearMonth::from_str("2018-04").unwrap());
-    let range_to = use_state_eq(|| {
-        let today = Local::now().date_naive();
-        YearMonth {
-            year: today.year(),
-            month: Month::from_u32(today.month()).unwrap(),
-        }
+    // From April 2018, or the earliest month a range up to now may start at
+    let range_from = use_state_eq(|| {
+        let earliest = YearMonth::current() - (MAX_MONTHS as i32 - 1);
+        YearMonth::from_str("2018-04").unwrap().max(earliest)
     });
+    let range_to = use_state_eq(YearMonth::current);
 
     let running = use_state_eq(|| false);
-    let progress = use_reducer_eq(|| Progress::new(&(*range_from..=*range_to)));
+    let progress = use_reducer_eq(|| Progress::new(&YearMonthRange::new(*range_from, *range_to)));
     let output = use_reducer_eq(Output::default);
+    let report = use_state_eq(|| None::<RunReport>);
+    let plan = use_state_eq(|| None::<Plan>);
+    // Months of the last download, listed in the status table
+    let run_months = use_state_eq(Vec::<YearMonth>::new);
     {
         let output = output.clone();
         let progress = progress.clone();
+        let report = report.clone();
         use_effect_with_deps(
             move |_| {
                 let output = output.clone();
@@ -165,8 +376,20 @@ pub fn app() -> HtmlResult {
                 spawn_local(async move {
                     let mut stream = event::listen::<Message>("output").await.unwrap();
                     while let Some(message) = stream.next().await {
-                        if let Message::Complete(_) = &message.payload {
-                            progress.dispatch(ProgressAction::Increment);
+                        match message.payload {
+                            Message::Report(payload) => {
+                                report.set(Some(payload));
+                                continue;
+                            }
+                            Message::Complete(_) | Message::Failed(..) => {
+                                progress.dispatch(ProgressAction::Increment);
+                            }
+                            Message::Message(_)
+                            | Message::Error(_)
+                            | Message::Started(_)
+                            | Message::Finished(_)
+                            | Message::Decision(..)
+                            | Message::Unavailable(_) => {}
                         }
                         output.dispatch(OutputAction::Message(message.payload));
                     }
@@ -228,103 +451,261 @@ pub fn app() -> HtmlResult {
             }
         })
     };
+    let apply_range_spec = |spec: RangeSpec| {
+        let range_from = range_from.clone();
+        let range_to = range_to.clone();
+        Callback::from(move |_| {
+            let range = spec.resolve(YearMonth::current());
+            range_from.set(range.start);
+            range_to.set(range.end);
+        })
+    };
+    let range_presets = [
+        (text(Text::ThisFiscalYear).into(), RangeSpec::ThisFiscalYear),
+        (text(Text::LastFiscalYear).into(), RangeSpec::LastFiscalYear),
+        (
+            Text::LastMonths.fill(*locale, &[&12]),
+            RangeSpec::LastMonths(12),
+        ),
+    ]
+    .into_iter()
+    .map(|(label, spec)| {
+        html! {
+          <button class="bg-white hover:bg-gray-100 text-indigo-600 py-1 px-3 rounded shadow" onclick={apply_range_spec(spec)}>{label}</button>
+        }
+    })
+    .collect::<Html>();
+    // Japanese eras mean little to English readers
+    let range_in_era = (*locale == Locale::Ja).then(|| {
+        let format = |month: YearMonth| {
+            EraYearMonth::from_year_month(month).map_or_else(String::new, |month| month.to_string())
+        };
+        format!("{}〜{}", format(*range_from), format(*range_to))
+    });
     {
         let range_from = range_from.clone();
         let range_to = range_to.clone();
         let progress = progress.clone();
         use_effect_with_deps(
             move |(range_from, range_to)| {
-                let total = YearMonth::iter_range(&(**range_from..=**range_to)).count();
+                let total = YearMonthRange::new(**range_from, **range_to).len();
                 progress.dispatch(ProgressAction::SetTotal(total));
             },
             (range_from, range_to),
         );
     }
 
-    let start = {
+    let output_format = use_state_eq(OutputFormat::default);
+    let change_output_format = {
+        let output_format = output_format.clone();
+        Callback::from(move |event: Event| {
+            let index = event
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .selected_index();
+            if let Some(format) = usize::try_from(index)
+                .ok()
+                .and_then(|index| OutputFormat::ALL.get(index))
+            {
+                output_format.set(*format);
+            }
+        })
+    };
+
+    let overwrite_policy = use_state_eq(OverwritePolicy::default);
+    let change_overwrite_policy = {
+        let overwrite_policy = overwrite_policy.clone();
+        Callback::from(move |event: Event| {
+            let index = event
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .selected_index();
+            if let Some(policy) = usize::try_from(index)
+                .ok()
+                .and_then(|index| OverwritePolicy::ALL.get(index))
+            {
+                overwrite_policy.set(*policy);
+            }
+        })
+    };
+
+    let form_errors = use_state_eq(FormErrors::default);
+    let create_save_location = use_state_eq(|| false);
+    let toggle_create_save_location = {
+        let create_save_location = create_save_location.clone();
+        Callback::from(move |event: Event| {
+            create_save_location.set(
+                event
+                    .target_unchecked_into::<web_sys::HtmlInputElement>()
+                    .checked(),
+            );
+        })
+    };
+    let run = {
+        let create_save_location = create_save_location.clone();
         let save_location = save_location.clone();
+        let output_format = output_format.clone();
+        let overwrite_policy = overwrite_policy.clone();
         let running = running.clone();
         let progress = progress.clone();
         let output = output.clone();
+        let report = report.clone();
+        let plan = plan.clone();
+        let run_months = run_months.clone();
+        let form_errors = form_errors.clone();
         let login_email_ref = login_email_ref.clone();
         let login_password_ref = login_password_ref.clone();
-        let range_from_ref = range_from_ref.clone();
-        let range_to_ref = range_to_ref.clone();
-        Callback::from(move |_| {
-            if *running {
-                return;
-            }
-            let running = running.clone();
-            let range_from_ref = range_from_ref.clone();
-            let range_to_ref = range_to_ref.clone();
-            let save_location = save_location.deref().clone();
-            let output = output.clone();
-            let login_email_ref = login_email_ref.clone();
-            let login_password_ref = login_password_ref.clone();
-            macro_rules! or_report {
-                ( $name:expr, $x:expr $(,)? ) => {
-                    match $x {
-                        Some(v) => v,
-                        None => {
-                            output.dispatch(OutputAction::Message(Message::error(format!(
-                                "不正な{}です。",
-                                $name,
-                            ))));
+        let passphrase_ref = passphrase_ref.clone();
+        let passphrase_confirm_ref = passphrase_confirm_ref.clone();
+        let locale = *locale;
+        Callback::from(
+            move |(mode, months): (RunMode, Result<Vec<YearMonth>, InputError>)| {
+                if *running {
+                    return;
+                }
+                let running = running.clone();
+                let save_location = save_location.deref().clone();
+                let output_format = *output_format;
+                let overwrite_policy = *overwrite_policy;
+                let output = output.clone();
+                let progress = progress.clone();
+                let report = report.clone();
+                let plan = plan.clone();
+                let run_months = run_months.clone();
+                let form_errors = form_errors.clone();
+                let create_save_location = *create_save_location;
+                let creds = Credentials {
+                    email: or_report!(
+                        output,
+                        locale,
+                        Text::EmailAddress,
+                        login_email_ref.cast::<web_sys::HtmlInputElement>()
+                    )
+                    .value()
+                    .trim()
+                    .to_string(),
+                    password: or_report!(
+                        output,
+                        locale,
+                        Text::Password,
+                        login_password_ref.cast::<web_sys::HtmlInputElement>()
+                    )
+                    .value(),
+                };
+                let value = |node: &NodeRef| {
+                    node.cast::<web_sys::HtmlInputElement>()
+                        .map(|element| element.value())
+                        .unwrap_or_default()
+                };
+                let passphrase = value(&passphrase_ref);
+                let passphrase_error =
+                    check_passphrase(&passphrase, &value(&passphrase_confirm_ref)).err();
+                let passphrase = Some(passphrase).filter(|passphrase| !passphrase.is_empty());
+                running.set(true);
+                spawn_local(async move {
+                    let errors = FormErrors {
+                        email: check_email(&creds.email).err(),
+                        password: check_password(&creds.password).err(),
+                        range: months.as_ref().err().copied(),
+                        // A missing folder is created by the backend on request
+                        save_location: check_save_location(save_location.as_deref())
+                            .await
+                            .err()
+                            .filter(|err| {
+                                !(create_save_location && *err == InputError::SaveLocationNotFound)
+                            }),
+                        passphrase: passphrase_error,
+                    };
+                    form_errors.set(errors);
+                    let (Ok(months), Some(save_location), true) =
+                        (months, save_location, errors.is_empty())
+                    else {
+                        running.set(false);
+                        return;
+                    };
+                    let args = to_value(&StartArgs {
+                        creds,
+                        months: months.clone(),
+                        save_location,
+                        output_format,
+                        overwrite_policy,
+                        passphrase,
+                        create_save_location,
+                    })
+                    .unwrap();
+                    plan.set(None);
+                    match mode {
+                        RunMode::DryRun => {
+                            output.dispatch(OutputAction::Clear);
+                            run_months.set(Vec::new());
+                            match invoke("plan", args).await {
+                                Ok(val) => plan.set(from_value(val).ok()),
+                                Err(err) => report_command_error(err, &form_errors, &output),
+                            }
+                            running.set(false);
                             return;
                         }
+                        RunMode::Download => {
+                            output.dispatch(OutputAction::Clear);
+                            run_months.set(months.clone());
+                        }
+                        RunMode::Retry => output.dispatch(OutputAction::Forget(months.clone())),
                     }
-                };
-            }
-            let creds = Credentials {
-                email: or_report!(
-                    "メールアドレス",
-                    login_email_ref.cast::<web_sys::HtmlInputElement>()
-                )
-                .value(),
-                password: or_report!(
-                    "パスワード",
-                    login_password_ref.cast::<web_sys::HtmlInputElement>()
-                )
-                .value(),
+                    progress.dispatch(ProgressAction::Reset);
+                    progress.dispatch(ProgressAction::SetTotal(months.len()));
+                    report.set(None);
+                    match invoke("start", args).await {
+                        Ok(val) => {
+                            if let Some(message) = val.as_string() {
+                                output.dispatch(OutputAction::Message(Message::message(message)));
+                            }
+                        }
+                        Err(err) => report_command_error(err, &form_errors, &output),
+                    }
+                    running.set(false);
+                });
+            },
+        )
+    };
+
+    let start = |mode: RunMode| {
+        let run = run.clone();
+        let range_from_ref = range_from_ref.clone();
+        let range_to_ref = range_to_ref.clone();
+        Callback::from(move |_: MouseEvent| {
+            let month = |node: &NodeRef| {
+                node.cast::<web_sys::HtmlInputElement>()
+                    .and_then(|element| YearMonth::from_str(&element.value()).ok())
+                    .ok_or(InputError::MonthMissing)
             };
-            let from = YearMonth::from_str(
-                &or_report!("年月", range_from_ref.cast::<web_sys::HtmlInputElement>()).value(),
-            )
-            .unwrap();
-            let to = YearMonth::from_str(
-                &or_report!("年月", range_to_ref.cast::<web_sys::HtmlInputElement>()).value(),
-            )
-            .unwrap();
-            let range = from..=to;
-            running.set(true);
-            progress.dispatch(ProgressAction::Reset);
-            output.dispatch(OutputAction::Clear);
-            spawn_local(async move {
-                let message = invoke(
-                    "start",
-                    to_value(&StartArgs {
-                        creds,
-                        range,
-                        save_location: save_location.unwrap(),
-                    })
-                    .unwrap(),
-                )
-                .await
-                .map(|val| val.as_string())
-                .map_err(|err| err.as_string());
-                let message = match message {
-                    Ok(Some(message)) => Some(Message::message(message)),
-                    Err(Some(err)) => Some(Message::error(err)),
-                    _ => None,
-                };
-                if let Some(message) = message {
-                    output.dispatch(OutputAction::Message(message));
-                }
-                running.set(false);
+            let months = month(&range_from_ref).and_then(|from| {
+                let range = YearMonthRange::new(from, month(&range_to_ref)?);
+                check_range(range, YearMonth::current())?;
+                Ok(range.iter().collect())
             });
+            run.emit((mode, months));
         })
     };
 
+    let download = start(RunMode::Download);
+    let dry_run = start(RunMode::DryRun);
+
+    let failed_months = output.failed_months();
+    let retry = {
+        let failed_months = failed_months.clone();
+        run.reform(move |_: MouseEvent| (RunMode::Retry, Ok(failed_months.clone())))
+    };
+    let retry_month = run.reform(|month: YearMonth| (RunMode::Retry, Ok(vec![month])));
+    let report_error = {
+        let output = output.clone();
+        Callback::from(move |err: String| {
+            output.dispatch(OutputAction::Message(Message::error(err)))
+        })
+    };
+
+    let field_error = |error: Option<InputError>| match error {
+        Some(error) => html! { <p class="mt-1 text-xs text-red-600">{error.message(*locale)}</p> },
+        None => html! {},
+    };
+
     let percent = format!("width: {:.0}%", progress.percent());
     let processed = progress.processed();
     let total = progress.total();
@@ -341,77 +722,175 @@ pub fn app() -> HtmlResult {
         )
     };
     Ok(html! {
-        <main class="flex flex-col h-screen bg-white sm:rounded-lg">
-          <div class="w-full border-b border-gray-200">
-            <dl>
-              <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
-                <dt class="text-sm font-medium text-gray-500">{"ログインメールアドレス"}</dt>
-                <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
-                  <input id="login-email" placeholder="you@example.com" class="w-full h-10 px-2 border-2 border-indigo-600/50 rounded-md" ref={login_email_ref} type="email" />
-                </dd>
-              </div>
-              <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
-                <dt class="text-sm font-medium text-gray-500">{"ログインパスワード"}</dt>
-                <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
-                  <input id="login-password" placeholder="Type your password" class="w-full h-10 px-2 border-2 border-indigo-600/50 rounded-md" ref={login_password_ref} type="password" />
-                </dd>
-              </div>
-              <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
-                <dt class="text-sm font-medium text-gray-500">{"期間"}</dt>
-                <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
-                  <div class="flex justify-between items-center">
-                    <input id="range-from" class="h-10 text-center border-2 border-indigo-600/50 rounded-md" ref={range_from_ref.clone()} type="month" value={format!("{}", &*range_from)} onchange={update_range(range_from, range_from_ref)} />
-                    <span class="text-bottom">{"〜"}</span>
-                    <input id="range-to" class="h-10 text-center border-2 border-indigo-600/50 rounded-md" ref={range_to_ref.clone()} type="month" value={format!("{}", &*range_to)} onchange={update_range(range_to, range_to_ref)} />
-                  </div>
-                </dd>
-              </div>
-              <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
-                <dt class="text-sm font-medium text-gray-500">{"保存先"}</dt>
-                <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
-                  <div class="flex items-center justify-between pl-3 pr-4 text-sm">
-                    <div class="flex w-0 flex-1 items-center">
-                      <span class="w-0 flex-1 truncate">
-                        { if let Some(location) = &*save_location {
-                            format!("{}", location.display())
-                        } else {
-                            "未設定".into()
-                        }}
-                      </span>
+        <ContextProvider<Locale> context={*locale}>
+          <main class="flex flex-col h-screen bg-white sm:rounded-lg">
+            <div class="flex justify-end items-center gap-2 px-4 pt-2 text-sm text-gray-500">
+              <span>{text(Text::Language)}</span>
+              <select class="h-8 px-2 border-2 border-indigo-600/50 rounded-md" onchange={change_locale}>
+              {
+                  Locale::ALL.iter().map(|option| html! {
+                    <option selected={*option == *locale}>{option.native_name()}</option>
+                  }).collect::<Html>()
+              }
+              </select>
+            </div>
+            <div class="w-full border-b border-gray-200">
+              <dl>
+                <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::LoginEmail)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <input id="login-email" placeholder="you@example.com" class="w-full h-10 px-2 border-2 border-indigo-600/50 rounded-md" ref={login_email_ref.clone()} type="email" />
+                    {field_error(form_errors.email)}
+                  </dd>
+                </div>
+                <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::LoginPassword)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <input id="login-password" placeholder={text(Text::PasswordPlaceholder)} class="w-full h-10 px-2 border-2 border-indigo-600/50 rounded-md" ref={login_password_ref.clone()} type="password" />
+                    {field_error(form_errors.password)}
+                  </dd>
+                </div>
+                <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::Period)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <div class="flex justify-between items-center">
+                      <input id="range-from" class="h-10 text-center border-2 border-indigo-600/50 rounded-md" ref={range_from_ref.clone()} type="month" value={format!("{}", &*range_from)} onchange={update_range(range_from, range_from_ref)} />
+                      <span class="text-bottom">{"〜"}</span>
+                      <input id="range-to" class="h-10 text-center border-2 border-indigo-600/50 rounded-md" ref={range_to_ref.clone()} type="month" value={format!("{}", &*range_to)} onchange={update_range(range_to, range_to_ref)} />
                     </div>
-                    <div class="ml-4 flex-shrink-0">
-                      <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={change_save_location}>{"変更"}</button>
+                    <div class="flex justify-between items-center mt-2">
+                      <span class="text-xs text-gray-500">{range_in_era}</span>
+                      <div class="flex gap-2">{range_presets}</div>
                     </div>
-                  </div>
-                </dd>
+                    {field_error(form_errors.range)}
+                  </dd>
+                </div>
+                <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::SaveLocation)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <div class="flex items-center justify-between pl-3 pr-4 text-sm">
+                      <div class="flex w-0 flex-1 items-center">
+                        <span class="w-0 flex-1 truncate">
+                          { if let Some(location) = &*save_location {
+                              format!("{}", location.display())
+                          } else {
+                              text(Text::NotSet).into()
+                          }}
+                        </span>
+                      </div>
+                      <div class="ml-4 flex-shrink-0">
+                        <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={change_save_location}>{text(Text::Change)}</button>
+                      </div>
+                    </div>
+                    {field_error(form_errors.save_location)}
+                    if form_errors.save_location == Some(InputError::SaveLocationNotFound) || *create_save_location {
+                      <label class="mt-1 flex items-center gap-2 text-xs text-gray-700">
+                        <input type="checkbox" checked={*create_save_location} onchange={toggle_create_save_location} />
+                        {text(Text::CreateSaveLocation)}
+                      </label>
+                    }
+                  </dd>
+                </div>
+                <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::OutputFormat)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <select class="h-10 px-2 border-2 border-indigo-600/50 rounded-md" onchange={change_output_format}>
+                    {
+                        OutputFormat::ALL.iter().map(|format| html! {
+                          <option selected={*format == *output_format}>{format.label(*locale)}</option>
+                        }).collect::<Html>()
+                    }
+                    </select>
+                  </dd>
+                </div>
+                <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::ExistingFiles)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <select class="h-10 px-2 border-2 border-indigo-600/50 rounded-md" onchange={change_overwrite_policy}>
+                    {
+                        OverwritePolicy::ALL.iter().map(|policy| html! {
+                          <option selected={*policy == *overwrite_policy}>{policy.label(*locale)}</option>
+                        }).collect::<Html>()
+                    }
+                    </select>
+                  </dd>
+                </div>
+                <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+                  <dt class="text-sm font-medium text-gray-500">{text(Text::Passphrase)}</dt>
+                  <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+                    <input id="passphrase" placeholder={text(Text::PassphrasePlaceholder)} class="w-full h-10 px-2 border-2 border-indigo-600/50 rounded-md" ref={passphrase_ref.clone()} type="password" />
+                    <input id="passphrase-confirm" placeholder={text(Text::PassphraseConfirmPlaceholder)} class="mt-2 w-full h-10 px-2 border-2 border-indigo-600/50 rounded-md" ref={passphrase_confirm_ref.clone()} type="password" />
+                    {field_error(form_errors.passphrase)}
+                    <p class="mt-2 text-xs text-gray-500">{text(Text::PassphraseHelp)}</p>
+                  </dd>
+                </div>
+                <ScheduleForm login_email_ref={login_email_ref} login_password_ref={login_password_ref} passphrase_ref={passphrase_ref} passphrase_confirm_ref={passphrase_confirm_ref} save_location={(*save_location).clone()} output_format={*output_format} overwrite_policy={*overwrite_policy} />
+                <BackupForm />
+                <NetworkForm />
+                <LogForm save_location={(*save_location).clone()} />
+              </dl>
+            </div>
+            <div class="w-full flex justify-center gap-4 my-8">
+              <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow text-sm" type="button" onclick={dry_run} disabled={*running}>
+                {text(Text::DryRun)}
+              </button>
+              <button class={classes!("group", "relative", "flex", "w-80", "items-center", "justify-center", "rounded-md", "border", "border-transparent", "py-2", "px-4", "text-sm", "font-medium", "text-white", download_button_classes)} type="button" onclick={download} disabled={*running} >
+                <svg class="fill-current w-4 h-4 mr-2" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20"><path d="M13 8V2H7v6H2l8 8 8-8h-5zM0 18h20v2H0v-2z"/></svg>
+                <span>{ text(if *running { Text::Downloading } else { Text::Download }) }</span>
+              </button>
+            </div>
+            <LoginStepForm />
+            if !*running && !failed_months.is_empty() {
+              <div class="w-full flex justify-center -mt-4 mb-4">
+                <button class="bg-white hover:bg-gray-100 text-red-600 py-2 px-4 rounded shadow text-sm" type="button" onclick={retry}>
+                  {Text::RetryFailed.fill(*locale, &[&failed_months.len()])}
+                </button>
               </div>
-            </dl>
-          </div>
-          <div class="w-full flex justify-center my-8">
-            <button class={classes!("group", "relative", "flex", "w-80", "items-center", "justify-center", "rounded-md", "border", "border-transparent", "py-2", "px-4", "text-sm", "font-medium", "text-white", download_button_classes)} type="button" onclick={start} disabled={*running} >
-              <svg class="fill-current w-4 h-4 mr-2" xmlns="http://www.w3.org/2000/svg" viewBox="0 0 20 20"><path d="M13 8V2H7v6H2l8 8 8-8h-5zM0 18h20v2H0v-2z"/></svg>
-              <span>{ if *running { "ダウンロード中..." } else { "ダウンロード" } }</span>
-            </button>
-          </div>
-          <div class="w-full flex justify-center items-center my-6">
-            <div class="w-5/6 bg-gray-200 rounded-full h-1.5">
-              <div class="flex-grow bg-indigo-600 h-1.5 rounded-full" style={percent}></div>
+            }
+            if let Some(text) = output.maintenance().and_then(|message| message.maintenance_text(*locale)) {
+              <div class="mx-auto w-5/6 rounded-md bg-amber-50 px-4 py-2 text-sm text-amber-800">{text}</div>
+            }
+            <div class="w-full flex justify-center items-center my-6">
+              <div class="w-5/6 bg-gray-200 rounded-full h-1.5">
+                <div class="flex-grow bg-indigo-600 h-1.5 rounded-full" style={percent}></div>
+              </div>
+              <span class="w-14 flex-none pl-5">{processed} {"/"} {total}</span>
             </div>
-            <span class="w-14 flex-none pl-5">{processed} {"/"} {total}</span>
-          </div>
-          <div class="h-full grow m-5 bg-gray-800 overflow-y-scroll rounded-lg">
-            <div class="h-full p-3 text-gray-100">
-            {
-                output.iter().enumerate().map(|(i, message)| {
-                    let text = match message {
-                        Message::Message(message) => Cow::from(message),
-                        Message::Error(err) => err.into(),
-                        Message::Complete(month) => format!("{month}").into(),
-                    };
-                    html! { <div key={i} class={classes!(message.is_err().then_some("text-red-400"))}>{ text }</div> }
-                }).collect::<Html>()
-            }</div>
-          </div>
-        </main>
+            if let Some(plan) = &*plan {
+              <PlanView plan={plan.clone()} />
+            }
+            if let Some(report) = &*report {
+              <Summary report={report.clone()} on_error={report_error.clone()} />
+            }
+            if !run_months.is_empty() {
+              <MonthTable months={(*run_months).clone()} messages={output.0.clone()} running={*running} on_retry={retry_month} on_error={report_error} />
+            }
+            <div class="h-full grow m-5 bg-gray-800 overflow-y-scroll rounded-lg">
+              <div class="h-full p-3 text-gray-100">
+              {
+                  output.general().enumerate().map(|(i, message)| {
+                      let text = match message {
+                          Message::Message(message) => Cow::from(message),
+                          Message::Error(err) => err.into(),
+                          Message::Unavailable(_) => message.maintenance_text(*locale).unwrap_or_default().into(),
+                          // Shown in the status table
+                          Message::Started(_)
+                          | Message::Finished(_)
+                          | Message::Complete(_)
+                          | Message::Failed(..)
+                          | Message::Decision(..)
+                          | Message::Report(_) => Cow::from(""),
+                      };
+                      let class = match message {
+                          Message::Unavailable(_) => Some("text-amber-300"),
+                          message => message.is_err().then_some("text-red-400"),
+                      };
+                      html! { <div key={i} class={classes!(class)}>{ text }</div> }
+                  }).collect::<Html>()
+              }</div>
+            </div>
+            <Viewer running={*running} />
+          </main>
+        </ContextProvider<Locale>>
     })
 }
@@ -0,0 +1,236 @@
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+use shared::{Locale, Settings, SinkConfig, Text};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::invoke;
+
+#[derive(Serialize)]
+struct AddSinkArgs {
+    sink: SinkConfig,
+}
+
+#[derive(Serialize)]
+struct RemoveSinkArgs {
+    index: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    LocalDir,
+    WebDav,
+    S3,
+}
+
+/// Values of the inputs of the form to add a backup target
+#[derive(Clone, Default, PartialEq)]
+struct Draft {
+    path: String,
+    url: String,
+    username: String,
+    password: String,
+    region: String,
+    bucket: String,
+    prefix: String,
+}
+
+impl Draft {
+    fn to_config(&self, kind: Kind) -> Option<SinkConfig> {
+        let sink = match kind {
+            Kind::LocalDir if !self.path.is_empty() => SinkConfig::LocalDir {
+                path: self.path.clone().into(),
+            },
+            Kind::WebDav if !self.url.is_empty() => SinkConfig::WebDav {
+                url: self.url.clone(),
+                username: self.username.clone(),
+                password: self.password.clone(),
+            },
+            Kind::S3 if !self.url.is_empty() && !self.bucket.is_empty() => SinkConfig::S3 {
+                endpoint: self.url.clone(),
+                region: if self.region.is_empty() {
+                    "us-east-1".into()
+                } else {
+                    self.region.clone()
+                },
+                bucket: self.bucket.clone(),
+                prefix: self.prefix.clone(),
+                access_key: self.username.clone(),
+                secret_key: self.password.clone(),
+            },
+            _ => return None,
+        };
+        Some(sink)
+    }
+}
+
+async fn load_sinks() -> Option<Vec<SinkConfig>> {
+    invoke("get_settings", to_value(&()).unwrap())
+        .await
+        .ok()
+        .and_then(|val| from_value::<Settings>(val).ok())
+        .map(|settings| settings.sinks)
+}
+
+/// Backup targets the exported files are copied to
+#[function_component(BackupForm)]
+pub fn backup_form() -> Html {
+    let sinks = use_state_eq(Vec::<SinkConfig>::new);
+    let kind = use_state_eq(|| Kind::LocalDir);
+    let draft = use_state_eq(Draft::default);
+    let error = use_state_eq(|| None::<String>);
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    {
+        let sinks = sinks.clone();
+        use_effect_with_deps(
+            |_| {
+                spawn_local(async move {
+                    if let Some(loaded) = load_sinks().await {
+                        sinks.set(loaded);
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    let select_kind = {
+        let kind = kind.clone();
+        Callback::from(move |event: Event| {
+            let value = event
+                .target_unchecked_into::<web_sys::HtmlSelectElement>()
+                .value();
+            kind.set(match value.as_str() {
+                "web_dav" => Kind::WebDav,
+                "s3" => Kind::S3,
+                _ => Kind::LocalDir,
+            });
+        })
+    };
+    let field = |update: fn(&mut Draft, String)| {
+        let draft = draft.clone();
+        Callback::from(move |event: InputEvent| {
+            let value = event
+                .target_unchecked_into::<web_sys::HtmlInputElement>()
+                .value();
+            let mut next = (*draft).clone();
+            update(&mut next, value);
+            draft.set(next);
+        })
+    };
+    let add = {
+        let sinks = sinks.clone();
+        let kind = kind.clone();
+        let draft = draft.clone();
+        let error = error.clone();
+        Callback::from(move |_| {
+            let Some(sink) = draft.to_config(*kind) else {
+                error.set(Some(Text::FillRequired.get(locale).into()));
+                return;
+            };
+            let sinks = sinks.clone();
+            let draft = draft.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                match invoke("add_sink", to_value(&AddSinkArgs { sink }).unwrap()).await {
+                    Ok(_) => {
+                        draft.set(Draft::default());
+                        error.set(None);
+                        if let Some(loaded) = load_sinks().await {
+                            sinks.set(loaded);
+                        }
+                    }
+                    Err(err) => error.set(err.as_string()),
+                }
+            });
+        })
+    };
+    let remove = |index: usize| {
+        let sinks = sinks.clone();
+        let error = error.clone();
+        Callback::from(move |_| {
+            let sinks = sinks.clone();
+            let error = error.clone();
+            spawn_local(async move {
+                match invoke("remove_sink", to_value(&RemoveSinkArgs { index }).unwrap()).await {
+                    Ok(_) => {
+                        if let Some(loaded) = load_sinks().await {
+                            sinks.set(loaded);
+                        }
+                    }
+                    Err(err) => error.set(err.as_string()),
+                }
+            });
+        })
+    };
+
+    let input = |placeholder: &'static str, value: &str, oninput: Callback<InputEvent>| {
+        html! {
+          <input class="h-8 w-full px-2 border-2 border-indigo-600/50 rounded-md" placeholder={placeholder} value={value.to_string()} {oninput} />
+        }
+    };
+    let password = |placeholder: &'static str, value: &str, oninput: Callback<InputEvent>| {
+        html! {
+          <input class="h-8 w-full px-2 border-2 border-indigo-600/50 rounded-md" type="password" placeholder={placeholder} value={value.to_string()} {oninput} />
+        }
+    };
+    let fields = match *kind {
+        Kind::LocalDir => input(
+            text(Text::FolderPath),
+            &draft.path,
+            field(|draft, value| draft.path = value),
+        ),
+        Kind::WebDav => html! {
+          <>
+            { input("https://example.com/remote.php/dav/files/user/ixsie", &draft.url, field(|draft, value| draft.url = value)) }
+            { input(text(Text::Username), &draft.username, field(|draft, value| draft.username = value)) }
+            { password(text(Text::Password), &draft.password, field(|draft, value| draft.password = value)) }
+          </>
+        },
+        Kind::S3 => html! {
+          <>
+            { input(text(Text::Endpoint), &draft.url, field(|draft, value| draft.url = value)) }
+            { input(text(Text::Region), &draft.region, field(|draft, value| draft.region = value)) }
+            { input(text(Text::Bucket), &draft.bucket, field(|draft, value| draft.bucket = value)) }
+            { input(text(Text::Prefix), &draft.prefix, field(|draft, value| draft.prefix = value)) }
+            { input(text(Text::AccessKey), &draft.username, field(|draft, value| draft.username = value)) }
+            { password(text(Text::SecretKey), &draft.password, field(|draft, value| draft.password = value)) }
+          </>
+        },
+    };
+
+    html! {
+      <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6">
+        <dt class="text-sm font-medium text-gray-500">{text(Text::BackupTargets)}</dt>
+        <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+          <ul class="mb-2">
+          {
+              sinks.iter().enumerate().map(|(index, sink)| html! {
+                <li class="flex items-center justify-between gap-2 py-1">
+                  <span class="truncate">{sink.name(locale)}</span>
+                  <button class="text-xs text-red-600 hover:underline" onclick={remove(index)}>{text(Text::Remove)}</button>
+                </li>
+              }).collect::<Html>()
+          }
+          </ul>
+          <div class="flex flex-col gap-2">
+            <select class="h-8 border-2 border-indigo-600/50 rounded-md" onchange={select_kind}>
+              <option value="local_dir" selected={*kind == Kind::LocalDir}>{text(Text::Folder)}</option>
+              <option value="web_dav" selected={*kind == Kind::WebDav}>{"WebDAV"}</option>
+              <option value="s3" selected={*kind == Kind::S3}>{text(Text::S3Storage)}</option>
+            </select>
+            {fields}
+            <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={add}>{text(Text::Add)}</button>
+          </div>
+          <p class="mt-2 text-xs text-gray-500">{text(Text::BackupHelp)}</p>
+          {
+              match &*error {
+                  Some(err) => html! { <p class="mt-1 text-xs text-red-600">{err}</p> },
+                  None => html! {},
+              }
+          }
+        </dd>
+      </div>
+    }
+}
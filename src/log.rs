@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use gloo_utils::format::JsValueSerdeExt;
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+use shared::{Locale, Text};
+use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::{invoke, open};
+
+#[derive(Serialize)]
+struct ExportLogArgs {
+    destination: PathBuf,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportDiagnosticsArgs {
+    destination: PathBuf,
+    save_location: Option<PathBuf>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct LogFormProps {
+    /// Where the report of the last run is looked for
+    pub save_location: Option<PathBuf>,
+}
+
+/// Asks for the folder an export goes to.
+async fn pick_directory() -> Option<PathBuf> {
+    #[derive(Serialize)]
+    struct Opts {
+        directory: bool,
+    }
+    let opts = <JsValue as JsValueSerdeExt>::from_serde(&Opts { directory: true }).unwrap();
+    open(opts).await.as_string().map(PathBuf::from)
+}
+
+/// Saves the result of `cmd`, the path of the written file, in `status`.
+async fn export(
+    cmd: &str,
+    args: JsValue,
+    locale: Locale,
+    status: UseStateHandle<Option<Result<String, String>>>,
+) {
+    let result = match invoke(cmd, args).await {
+        Ok(path) => Ok(Text::ExportedTo.fill(
+            locale,
+            &[&from_value::<PathBuf>(path).unwrap_or_default().display()],
+        )),
+        Err(err) => Err(err.as_string().unwrap_or_default()),
+    };
+    status.set(Some(result));
+}
+
+/// Buttons to open or export the diagnostic log
+#[function_component(LogForm)]
+pub fn log_form(props: &LogFormProps) -> Html {
+    let status = use_state_eq(|| None::<Result<String, String>>);
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    let open_dir = {
+        let status = status.clone();
+        Callback::from(move |_| {
+            let status = status.clone();
+            spawn_local(async move {
+                if let Err(err) = invoke("open_log_dir", to_value(&()).unwrap()).await {
+                    status.set(Some(Err(err.as_string().unwrap_or_default())));
+                }
+            });
+        })
+    };
+    let export_log = {
+        let status = status.clone();
+        Callback::from(move |_| {
+            let status = status.clone();
+            spawn_local(async move {
+                let Some(destination) = pick_directory().await else {
+                    return;
+                };
+                let args = to_value(&ExportLogArgs { destination }).unwrap();
+                export("export_log", args, locale, status).await;
+            });
+        })
+    };
+    let export_diagnostics = {
+        let status = status.clone();
+        let save_location = props.save_location.clone();
+        Callback::from(move |_| {
+            let status = status.clone();
+            let save_location = save_location.clone();
+            spawn_local(async move {
+                let Some(destination) = pick_directory().await else {
+                    return;
+                };
+                let args = to_value(&ExportDiagnosticsArgs {
+                    destination,
+                    save_location,
+                })
+                .unwrap();
+                export("export_diagnostics", args, locale, status).await;
+            });
+        })
+    };
+
+    html! {
+      <div class="bg-gray-50 px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6">
+        <dt class="text-sm font-medium text-gray-500">{text(Text::Log)}</dt>
+        <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+          <div class="flex gap-2">
+            <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={open_dir}>{text(Text::OpenFolder)}</button>
+            <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={export_log}>{text(Text::ExportLog)}</button>
+            <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={export_diagnostics}>{text(Text::ExportDiagnostics)}</button>
+          </div>
+          <p class="mt-2 text-xs text-gray-500">{text(Text::LogHelp)}</p>
+          {
+              match &*status {
+                  Some(Ok(message)) => html! { <p class="mt-1 text-xs text-indigo-600">{message}</p> },
+                  Some(Err(err)) => html! { <p class="mt-1 text-xs text-red-600">{err}</p> },
+                  None => html! {},
+              }
+          }
+        </dd>
+      </div>
+    }
+}
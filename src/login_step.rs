@@ -0,0 +1,71 @@
+use futures::StreamExt;
+use serde::Serialize;
+use serde_wasm_bindgen::to_value;
+use shared::{Locale, LoginStep, Text};
+use tauri_sys::event;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::invoke;
+
+#[derive(Serialize)]
+struct SubmitLoginStepArgs {
+    answer: Option<String>,
+}
+
+/// Input for an extra sign-in step, shown while an export waits for it
+#[function_component(LoginStepForm)]
+pub fn login_step_form() -> Html {
+    let step = use_state_eq(|| None::<LoginStep>);
+    let code_ref = use_node_ref();
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    {
+        let step = step.clone();
+        use_effect_with_deps(
+            move |_| {
+                spawn_local(async move {
+                    let mut stream = event::listen::<LoginStep>("login_step").await.unwrap();
+                    while let Some(event) = stream.next().await {
+                        step.set(Some(event.payload));
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    let answer = |submit: bool| {
+        let step = step.clone();
+        let code_ref = code_ref.clone();
+        Callback::from(move |_| {
+            let answer = submit.then(|| {
+                code_ref
+                    .cast::<web_sys::HtmlInputElement>()
+                    .map(|input| input.value())
+                    .unwrap_or_default()
+            });
+            step.set(None);
+            spawn_local(async move {
+                let args = SubmitLoginStepArgs { answer };
+                let _ = invoke("submit_login_step", to_value(&args).unwrap()).await;
+            });
+        })
+    };
+
+    let Some(login_step) = &*step else {
+        return html! {};
+    };
+    html! {
+      <div class="mx-auto my-4 w-5/6 rounded-md border-2 border-indigo-600/50 bg-white px-4 py-3">
+        <p class="text-sm font-medium text-gray-700">{text(Text::Verification)}</p>
+        <p class="mt-1 text-sm text-gray-900">{&login_step.message}</p>
+        <div class="mt-2 flex items-center gap-2">
+          <input class="h-10 flex-1 px-2 border-2 border-indigo-600/50 rounded-md" ref={code_ref.clone()}
+            placeholder={text(Text::VerificationCode)} autocomplete="one-time-code" />
+          <button class="bg-indigo-600 hover:bg-indigo-700 text-white py-2 px-4 rounded shadow text-sm" type="button" onclick={answer(true)}>{text(Text::Submit)}</button>
+          <button class="bg-white hover:bg-gray-100 text-gray-600 py-2 px-4 rounded shadow text-sm" type="button" onclick={answer(false)}>{text(Text::Cancel)}</button>
+        </div>
+      </div>
+    }
+}
@@ -1,4 +1,13 @@
 mod app;
+mod backup;
+mod log;
+mod login_step;
+mod months;
+mod network;
+mod plan;
+mod schedule;
+mod summary;
+mod viewer;
 
 use app::App;
 
@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde_wasm_bindgen::to_value;
+use shared::{format_size, FileAction, Locale, Message, MonthReport, MonthStatus, Text, YearMonth};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::invoke;
+
+#[derive(Serialize)]
+struct OpenExportedArgs {
+    path: PathBuf,
+}
+
+/// Opens an exported file with the default application, which the backend
+/// only allows inside the save location
+pub fn open_exported(path: PathBuf, on_error: Callback<String>) -> Callback<MouseEvent> {
+    Callback::from(move |_| {
+        let args = OpenExportedArgs { path: path.clone() };
+        let on_error = on_error.clone();
+        spawn_local(async move {
+            if let Err(err) = invoke("open_exported", to_value(&args).unwrap()).await {
+                on_error.emit(err.as_string().unwrap_or_default());
+            }
+        });
+    })
+}
+
+#[derive(Properties, PartialEq)]
+pub struct MonthTableProps {
+    /// Months of the current run
+    pub months: Vec<YearMonth>,
+    /// Progress messages of the run
+    pub messages: Vec<Message>,
+    pub running: bool,
+    pub on_retry: Callback<YearMonth>,
+    /// Errors of opening files go to the output view
+    pub on_error: Callback<String>,
+}
+
+/// Where a month of the current run is
+enum Row<'a> {
+    Pending,
+    Downloading,
+    Finished(&'a MonthReport),
+}
+
+impl<'a> Row<'a> {
+    /// The latest state of `month` according to `messages`
+    fn of(month: YearMonth, messages: &'a [Message]) -> Self {
+        messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Finished(report) if report.month == month => Some(Row::Finished(report)),
+                Message::Started(started) if *started == month => Some(Row::Downloading),
+                _ => None,
+            })
+            .unwrap_or(Row::Pending)
+    }
+}
+
+/// One row per month of the current run with its status, updated as the
+/// backend reports progress
+#[function_component(MonthTable)]
+pub fn month_table(props: &MonthTableProps) -> Html {
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    let open = |path: PathBuf| open_exported(path, props.on_error.clone());
+    let retry = |month: YearMonth| props.on_retry.reform(move |_: MouseEvent| month);
+    let button = "bg-white hover:bg-gray-100 text-indigo-600 py-0.5 px-2 rounded shadow text-xs";
+    html! {
+      <div class="mx-5 max-h-96 overflow-y-auto">
+        <table class="w-full text-sm text-left">
+          <thead class="text-gray-500 border-b border-gray-200">
+            <tr>
+              <th class="py-1">{text(Text::Month)}</th>
+              <th class="py-1">{text(Text::Status)}</th>
+              <th class="py-1">{text(Text::Size)}</th>
+              <th class="py-1">{text(Text::Duration)}</th>
+              <th class="py-1"></th>
+            </tr>
+          </thead>
+          <tbody>
+          {
+              props.months.iter().map(|&month| {
+                  let (status, class, report) = match Row::of(month, &props.messages) {
+                      Row::Pending => (text(Text::Pending), "text-gray-400", None),
+                      Row::Downloading => (text(Text::InProgress), "text-indigo-600", None),
+                      Row::Finished(report) if report.status == MonthStatus::Failed => {
+                          (text(Text::Failed), "text-red-600", Some(report))
+                      }
+                      Row::Finished(report) if report.action == Some(FileAction::Skipped) => {
+                          (FileAction::Skipped.label(locale), "text-gray-500", Some(report))
+                      }
+                      Row::Finished(report) => (text(Text::Done), "text-gray-900", Some(report)),
+                  };
+                  let size = report
+                      .and_then(|report| report.size)
+                      .map(format_size)
+                      .unwrap_or_default();
+                  let duration = report
+                      .map(|report| {
+                          Text::Seconds.fill(locale, &[&format!("{:.1}", report.duration_ms as f64 / 1000.0)])
+                      })
+                      .unwrap_or_default();
+                  // Months in an archive have no file of their own
+                  let path = report
+                      .filter(|report| report.status == MonthStatus::Done)
+                      .and_then(|report| report.path.clone())
+                      .filter(|path| path.is_absolute());
+                  let failed = report.is_some_and(|report| report.status == MonthStatus::Failed);
+                  html! {
+                    <tr key={month.to_string()} class="border-b border-gray-100">
+                      <td class="py-1">{month.to_string()}</td>
+                      <td class={classes!("py-1", class)} title={report.and_then(|report| report.error.clone())}>{status}</td>
+                      <td class="py-1">{size}</td>
+                      <td class="py-1">{duration}</td>
+                      <td class="py-1 text-right">
+                        if let Some(path) = path {
+                          <button class={button} type="button" onclick={open(path)}>{text(Text::Open)}</button>
+                        }
+                        if failed && !props.running {
+                          <button class={button} type="button" onclick={retry(month)}>{text(Text::Retry)}</button>
+                        }
+                      </td>
+                    </tr>
+                  }
+              }).collect::<Html>()
+          }
+          </tbody>
+        </table>
+      </div>
+    }
+}
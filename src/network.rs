@@ -0,0 +1,153 @@
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+use shared::{Locale, NetworkSettings, Settings, Text, MAX_MAINTENANCE_WAIT_MINS};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::invoke;
+
+#[derive(Serialize)]
+struct SaveNetworkSettingsArgs {
+    network: NetworkSettings,
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.is_empty())
+}
+
+/// Empty or zero means no timeout, or no waiting
+fn positive(value: String) -> Option<u64> {
+    value.parse().ok().filter(|value| *value > 0)
+}
+
+fn maintenance_wait(value: String) -> Option<u64> {
+    positive(value).map(|mins| mins.min(MAX_MAINTENANCE_WAIT_MINS))
+}
+
+/// Proxy, certificates and timeouts of the HTTP client
+#[function_component(NetworkForm)]
+pub fn network_form() -> Html {
+    let network = use_state_eq(NetworkSettings::default);
+    let status = use_state_eq(|| None::<Result<String, String>>);
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    {
+        let network = network.clone();
+        use_effect_with_deps(
+            |_| {
+                spawn_local(async move {
+                    let settings = invoke("get_settings", to_value(&()).unwrap())
+                        .await
+                        .ok()
+                        .and_then(|val| from_value::<Settings>(val).ok());
+                    if let Some(settings) = settings {
+                        network.set(settings.network);
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    let field = |update: fn(&mut NetworkSettings, String)| {
+        let network = network.clone();
+        Callback::from(move |event: Event| {
+            let value = event
+                .target_unchecked_into::<web_sys::HtmlInputElement>()
+                .value();
+            let mut next = (*network).clone();
+            update(&mut next, value.trim().to_string());
+            network.set(next);
+        })
+    };
+    let change_certificates = {
+        let network = network.clone();
+        Callback::from(move |event: Event| {
+            let value = event
+                .target_unchecked_into::<web_sys::HtmlTextAreaElement>()
+                .value();
+            network.set(NetworkSettings {
+                root_certificates: value
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(Into::into)
+                    .collect(),
+                ..(*network).clone()
+            });
+        })
+    };
+    let save = {
+        let network = network.clone();
+        let status = status.clone();
+        Callback::from(move |_| {
+            let args = SaveNetworkSettingsArgs {
+                network: (*network).clone(),
+            };
+            let status = status.clone();
+            spawn_local(async move {
+                let result = invoke("save_network_settings", to_value(&args).unwrap())
+                    .await
+                    .map(|_| Text::Saved.get(locale).to_string())
+                    .map_err(|err| err.as_string().unwrap_or_default());
+                status.set(Some(result));
+            });
+        })
+    };
+
+    let certificates = network
+        .root_certificates
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let input_class = "h-8 w-full px-2 border-2 border-indigo-600/50 rounded-md";
+    html! {
+      <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6">
+        <dt class="text-sm font-medium text-gray-500">{text(Text::Network)}</dt>
+        <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+          <div class="flex flex-col gap-2">
+            <input class={input_class} placeholder={text(Text::ProxyPlaceholder)}
+              value={network.proxy.clone().unwrap_or_default()}
+              onchange={field(|network, value| network.proxy = non_empty(value))} />
+            <textarea class="w-full px-2 border-2 border-indigo-600/50 rounded-md" rows="2" placeholder={text(Text::CertificatesPlaceholder)}
+              value={certificates} onchange={change_certificates} />
+            <input class={input_class} placeholder={text(Text::UserAgentPlaceholder)}
+              value={network.user_agent.clone().unwrap_or_default()}
+              onchange={field(|network, value| network.user_agent = non_empty(value))} />
+            <div class="flex items-center gap-2">
+              <label class="flex-1">{text(Text::ConnectTimeout)}
+                <input class={input_class} type="number" min="0"
+                  value={network.connect_timeout_secs.map(|secs| secs.to_string()).unwrap_or_default()}
+                  onchange={field(|network, value| network.connect_timeout_secs = positive(value))} />
+              </label>
+              <label class="flex-1">{text(Text::TotalTimeout)}
+                <input class={input_class} type="number" min="0"
+                  value={network.timeout_secs.map(|secs| secs.to_string()).unwrap_or_default()}
+                  onchange={field(|network, value| network.timeout_secs = positive(value))} />
+              </label>
+              <label class="flex-1">{text(Text::IdleTimeout)}
+                <input class={input_class} type="number" min="0"
+                  value={network.idle_timeout_secs.map(|secs| secs.to_string()).unwrap_or_default()}
+                  onchange={field(|network, value| network.idle_timeout_secs = positive(value))} />
+              </label>
+            </div>
+            <label>{text(Text::MaintenanceWait)}
+              <input class={input_class} type="number" min="0" max={MAX_MAINTENANCE_WAIT_MINS.to_string()}
+                value={network.maintenance_wait_mins.map(|mins| mins.to_string()).unwrap_or_default()}
+                onchange={field(|network, value| network.maintenance_wait_mins = maintenance_wait(value))} />
+            </label>
+            <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={save}>{text(Text::Save)}</button>
+          </div>
+          <p class="mt-2 text-xs text-gray-500">{text(Text::NetworkHelp)}</p>
+          {
+              match &*status {
+                  Some(Ok(message)) => html! { <p class="mt-1 text-xs text-indigo-600">{message}</p> },
+                  Some(Err(err)) => html! { <p class="mt-1 text-xs text-red-600">{err}</p> },
+                  None => html! {},
+              }
+          }
+        </dd>
+      </div>
+    }
+}
@@ -0,0 +1,59 @@
+use shared::{Locale, Plan, PlanAction, Text};
+use yew::prelude::*;
+
+#[derive(Properties, PartialEq)]
+pub struct PlanViewProps {
+    pub plan: Plan,
+}
+
+/// What a download would write, shown after a dry run
+#[function_component(PlanView)]
+pub fn plan_view(props: &PlanViewProps) -> Html {
+    let plan = &props.plan;
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    let counts = PlanAction::ALL
+        .iter()
+        .map(|&action| (action, plan.count(action)))
+        .filter(|&(_, count)| count > 0)
+        .map(|(action, count)| Text::ActionCount.fill(locale, &[&action.label(locale), &count]))
+        .collect::<Vec<_>>()
+        .join(" / ");
+    let action_class = |action| match action {
+        PlanAction::Overwrite | PlanAction::IfChanged => "text-amber-600",
+        PlanAction::Download | PlanAction::Skip | PlanAction::KeepBoth => "text-gray-900",
+    };
+    html! {
+      <div class="m-5">
+        <div class="text-sm text-gray-700 mb-2">
+          {Text::PlanHeader.fill(locale, &[&plan.range, &counts])}
+        </div>
+        <div class="text-sm text-gray-700 mb-2 truncate">{format!("{}: {}", text(Text::SaveLocation), plan.save_location.display())}</div>
+        if let Some(archive) = &plan.archive {
+          <div class={classes!("text-sm", "mb-2", "truncate", action_class(archive.action))}>
+            {format!("{}: {} ({})", text(Text::Archive), archive.path.display(), archive.action.label(locale))}
+          </div>
+        }
+        <table class="w-full text-sm text-left">
+          <thead class="text-gray-500 border-b border-gray-200">
+            <tr>
+              <th class="py-1">{text(Text::Month)}</th>
+              <th class="py-1">{text(Text::Action)}</th>
+              <th class="py-1">{text(Text::SaveLocation)}</th>
+            </tr>
+          </thead>
+          <tbody>
+          {
+              plan.months.iter().map(|month| html! {
+                <tr key={month.month.to_string()} class="border-b border-gray-100">
+                  <td class="py-1">{month.month.to_string()}</td>
+                  <td class={classes!("py-1", action_class(month.action))}>{month.action.label(locale)}</td>
+                  <td class="py-1 truncate" title={month.previous_sha256.clone()}>{month.path.display().to_string()}</td>
+                </tr>
+              }).collect::<Html>()
+          }
+          </tbody>
+        </table>
+      </div>
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+use serde_wasm_bindgen::{from_value, to_value};
+use shared::{
+    check_passphrase, Credentials, Locale, OutputFormat, OverwritePolicy, Schedule, Settings, Text,
+};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::invoke;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveScheduleArgs {
+    schedule: Schedule,
+    creds: Option<Credentials>,
+    save_location: Option<PathBuf>,
+    output_format: OutputFormat,
+    overwrite_policy: OverwritePolicy,
+    passphrase: Option<String>,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ScheduleFormProps {
+    pub login_email_ref: NodeRef,
+    pub login_password_ref: NodeRef,
+    pub passphrase_ref: NodeRef,
+    pub passphrase_confirm_ref: NodeRef,
+    pub save_location: Option<PathBuf>,
+    pub output_format: OutputFormat,
+    pub overwrite_policy: OverwritePolicy,
+}
+
+/// Settings of the daily background export
+#[function_component(ScheduleForm)]
+pub fn schedule_form(props: &ScheduleFormProps) -> Html {
+    let schedule = use_state_eq(Schedule::default);
+    let status = use_state_eq(|| None::<Result<String, String>>);
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    {
+        let schedule = schedule.clone();
+        use_effect_with_deps(
+            |_| {
+                spawn_local(async move {
+                    let settings = invoke("get_settings", to_value(&()).unwrap())
+                        .await
+                        .ok()
+                        .and_then(|val| from_value::<Settings>(val).ok());
+                    if let Some(settings) = settings {
+                        schedule.set(settings.schedule);
+                    }
+                });
+            },
+            (),
+        );
+    }
+
+    let toggle = {
+        let schedule = schedule.clone();
+        Callback::from(move |event: Event| {
+            let enabled = event
+                .target_unchecked_into::<web_sys::HtmlInputElement>()
+                .checked();
+            schedule.set(Schedule {
+                enabled,
+                ..*schedule
+            });
+        })
+    };
+    let change_time = {
+        let schedule = schedule.clone();
+        Callback::from(move |event: Event| {
+            let value = event
+                .target_unchecked_into::<web_sys::HtmlInputElement>()
+                .value();
+            if let Some((hour, minute)) = value.split_once(':') {
+                if let (Ok(hour), Ok(minute)) = (hour.parse(), minute.parse()) {
+                    schedule.set(Schedule {
+                        hour,
+                        minute,
+                        ..*schedule
+                    });
+                }
+            }
+        })
+    };
+    let save = {
+        let schedule = schedule.clone();
+        let status = status.clone();
+        let login_email_ref = props.login_email_ref.clone();
+        let login_password_ref = props.login_password_ref.clone();
+        let passphrase_ref = props.passphrase_ref.clone();
+        let passphrase_confirm_ref = props.passphrase_confirm_ref.clone();
+        let save_location = props.save_location.clone();
+        let output_format = props.output_format;
+        let overwrite_policy = props.overwrite_policy;
+        Callback::from(move |_| {
+            let value = |node: &NodeRef| {
+                node.cast::<web_sys::HtmlInputElement>()
+                    .map(|element| element.value())
+                    .unwrap_or_default()
+            };
+            let passphrase = value(&passphrase_ref);
+            if let Err(err) = check_passphrase(&passphrase, &value(&passphrase_confirm_ref)) {
+                status.set(Some(Err(err.message(locale))));
+                return;
+            }
+            let args = SaveScheduleArgs {
+                schedule: *schedule,
+                creds: Some(Credentials {
+                    email: value(&login_email_ref),
+                    password: value(&login_password_ref),
+                }),
+                save_location: save_location.clone(),
+                output_format,
+                overwrite_policy,
+                passphrase: Some(passphrase),
+            };
+            let status = status.clone();
+            spawn_local(async move {
+                let result = invoke("save_schedule", to_value(&args).unwrap())
+                    .await
+                    .map(|_| Text::Saved.get(locale).to_string())
+                    .map_err(|err| err.as_string().unwrap_or_default());
+                status.set(Some(result));
+            });
+        })
+    };
+
+    let time = format!("{:02}:{:02}", schedule.hour, schedule.minute);
+    html! {
+      <div class="bg-white px-4 py-5 sm:grid sm:grid-cols-3 sm:gap-4 sm:px-6 items-center">
+        <dt class="text-sm font-medium text-gray-500">{text(Text::ScheduledExport)}</dt>
+        <dd class="mt-1 text-sm text-gray-900 sm:col-span-2 sm:mt-0">
+          <div class="flex items-center justify-between gap-2">
+            <label class="flex items-center gap-2">
+              <input type="checkbox" checked={schedule.enabled} onchange={toggle} />
+              <span>{text(Text::Daily)}</span>
+            </label>
+            <input class="h-10 text-center border-2 border-indigo-600/50 rounded-md" type="time" value={time} onchange={change_time} />
+            <button class="bg-white hover:bg-gray-100 text-indigo-600 py-2 px-4 rounded shadow" onclick={save}>{text(Text::Save)}</button>
+          </div>
+          <p class="mt-2 text-xs text-gray-500">{text(Text::ScheduleHelp)}</p>
+          {
+              match &*status {
+                  Some(Ok(message)) => html! { <p class="mt-1 text-xs text-indigo-600">{message}</p> },
+                  Some(Err(err)) => html! { <p class="mt-1 text-xs text-red-600">{err}</p> },
+                  None => html! {},
+              }
+          }
+        </dd>
+      </div>
+    }
+}
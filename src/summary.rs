@@ -0,0 +1,102 @@
+use serde_wasm_bindgen::to_value;
+use shared::{format_size, Locale, MonthStatus, RunReport, Text};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::{app::invoke, months::open_exported};
+
+#[derive(Properties, PartialEq)]
+pub struct SummaryProps {
+    pub report: RunReport,
+    /// Errors of opening files go to the output view
+    pub on_error: Callback<String>,
+}
+
+/// Per-month results of the last run
+#[function_component(Summary)]
+pub fn summary(props: &SummaryProps) -> Html {
+    let report = &props.report;
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    let done = report.count(MonthStatus::Done);
+    let failed = report.count(MonthStatus::Failed);
+    let changed = report
+        .changed_months()
+        .map(|month| month.to_string())
+        .collect::<Vec<_>>();
+    let reveal = {
+        let on_error = props.on_error.clone();
+        Callback::from(move |_| {
+            let on_error = on_error.clone();
+            spawn_local(async move {
+                if let Err(err) = invoke("reveal_save_location", to_value(&()).unwrap()).await {
+                    on_error.emit(err.as_string().unwrap_or_default());
+                }
+            });
+        })
+    };
+    let button = "bg-white hover:bg-gray-100 text-indigo-600 py-0.5 px-2 rounded shadow text-xs";
+    html! {
+      <div class="m-5">
+        <div class="flex items-center justify-between text-sm text-gray-700 mb-2">
+          <span>{Text::Counts.fill(locale, &[&done, &failed, &format_size(report.total_size())])}</span>
+          <button class={button} type="button" onclick={reveal}>{text(Text::OpenSaveLocation)}</button>
+        </div>
+        if let Some(archive) = &report.archive {
+          <div class="flex items-center gap-2 text-sm text-gray-700 mb-2">
+            <span class="truncate">{format!("{}: {}", text(Text::Archive), archive.display())}</span>
+            <button class={button} type="button" onclick={open_exported(archive.clone(), props.on_error.clone())}>{text(Text::Open)}</button>
+          </div>
+        }
+        if !changed.is_empty() {
+          <div class="text-sm text-amber-600 mb-2">{format!("{}: {}", text(Text::ChangedMonths), changed.join(", "))}</div>
+        }
+        <table class="w-full text-sm text-left">
+          <thead class="text-gray-500 border-b border-gray-200">
+            <tr>
+              <th class="py-1">{text(Text::Month)}</th>
+              <th class="py-1">{text(Text::Status)}</th>
+              <th class="py-1">{text(Text::Action)}</th>
+              <th class="py-1">{text(Text::Size)}</th>
+              <th class="py-1">{text(Text::Duration)}</th>
+              <th class="py-1">{text(Text::Backup)}</th>
+              <th class="py-1">{text(Text::Error)}</th>
+            </tr>
+          </thead>
+          <tbody>
+          {
+              report.months.iter().map(|month| {
+                  let status_class = match month.status {
+                      MonthStatus::Done => "text-gray-900",
+                      MonthStatus::Failed => "text-red-600",
+                  };
+                  html! {
+                    <tr key={month.month.to_string()} class="border-b border-gray-100">
+                      <td class="py-1">{month.month.to_string()}</td>
+                      <td class={classes!("py-1", status_class)}>{month.status.label(locale)}</td>
+                      <td class="py-1" title={month.previous_version.as_ref().map(|version| version.display().to_string())}>
+                        {month.action.map(|action| action.label(locale)).unwrap_or_default()}
+                        if month.changed {
+                          <span class="ml-1 text-amber-600">{text(Text::Changed)}</span>
+                        }
+                      </td>
+                      <td class="py-1">{month.size.map(format_size).unwrap_or_default()}</td>
+                      <td class="py-1">{Text::Seconds.fill(locale, &[&format!("{:.1}", month.duration_ms as f64 / 1000.0)])}</td>
+                      <td class="py-1">
+                      {
+                          month.sinks.iter().map(|sink| match &sink.error {
+                              Some(err) => html! { <div class="text-red-600" title={err.clone()}>{format!("✗ {}", sink.name)}</div> },
+                              None => html! { <div>{format!("✓ {}", sink.name)}</div> },
+                          }).collect::<Html>()
+                      }
+                      </td>
+                      <td class="py-1 text-red-600">{month.error.clone().unwrap_or_default()}</td>
+                    </tr>
+                  }
+              }).collect::<Html>()
+          }
+          </tbody>
+        </table>
+      </div>
+    }
+}
@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use serde_wasm_bindgen::{from_value, to_value};
+use shared::{format_size, ExportedMonth, Locale, Text};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::app::{convert_file_src, invoke};
+
+/// Scheme of the backend serving the exported PDFs
+const PROTOCOL: &str = "exports";
+
+#[derive(Properties, PartialEq)]
+pub struct ViewerProps {
+    /// The list is read again whenever a run ends
+    pub running: bool,
+}
+
+/// Exported months of the save locations next to the selected PDF
+#[function_component(Viewer)]
+pub fn viewer(props: &ViewerProps) -> Html {
+    let months = use_state_eq(Vec::<ExportedMonth>::new);
+    let selected = use_state_eq(|| None::<PathBuf>);
+    let locale = use_context::<Locale>().unwrap_or_default();
+    let text = |key: Text| key.get(locale);
+    let reload = {
+        let months = months.clone();
+        Callback::from(move |_| {
+            let months = months.clone();
+            spawn_local(async move {
+                if let Ok(val) = invoke("list_exported", to_value(&()).unwrap()).await {
+                    months.set(from_value(val).unwrap_or_default());
+                }
+            });
+        })
+    };
+    {
+        let reload = reload.clone();
+        use_effect_with_deps(
+            move |running| {
+                if !*running {
+                    reload.emit(());
+                }
+            },
+            props.running,
+        );
+    }
+
+    let select = |path: PathBuf| {
+        let selected = selected.clone();
+        Callback::from(move |_: MouseEvent| selected.set(Some(path.clone())))
+    };
+    let src = (*selected)
+        .as_ref()
+        .filter(|path| months.iter().any(|month| &month.path == *path))
+        .and_then(|path| path.to_str())
+        .map(|path| convert_file_src(path, PROTOCOL));
+    html! {
+      <div class="m-5">
+        <div class="flex items-center justify-between mb-2">
+          <h2 class="text-sm font-medium text-gray-700">{text(Text::Viewer)}</h2>
+          <button class="bg-white hover:bg-gray-100 text-indigo-600 py-0.5 px-2 rounded shadow text-xs" type="button" onclick={reload.reform(|_: MouseEvent| ())}>
+            {text(Text::Reload)}
+          </button>
+        </div>
+        if months.is_empty() {
+          <p class="text-sm text-gray-500">{text(Text::NoExportedMonths)}</p>
+        } else {
+          <div class="flex gap-4 h-[36rem]">
+            <ul class="w-40 flex-none overflow-y-auto text-sm">
+            {
+                months.iter().map(|month| {
+                    let class = if selected.as_ref() == Some(&month.path) {
+                        "bg-indigo-600 text-white"
+                    } else {
+                        "hover:bg-gray-100 text-gray-900"
+                    };
+                    html! {
+                      <li key={month.path.display().to_string()}>
+                        <button class={classes!("w-full", "flex", "justify-between", "px-2", "py-1", "rounded", class)} type="button"
+                          title={month.path.display().to_string()} onclick={select(month.path.clone())}>
+                          <span>{month.month.to_string()}</span>
+                          <span class="text-xs opacity-75">{format_size(month.size)}</span>
+                        </button>
+                      </li>
+                    }
+                }).collect::<Html>()
+            }
+            </ul>
+            if let Some(src) = src {
+              <iframe class="flex-1 rounded border border-gray-200" src={src} />
+            }
+          </div>
+        }
+      </div>
+    }
+}